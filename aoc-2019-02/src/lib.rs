@@ -2,6 +2,9 @@
 
 use std::fs;
 
+mod symbolic;
+use symbolic::{Constraint, Expr, Intcode, PathEnd, SymbolicExecutor};
+
 struct Computer {
     memory: Vec<i32>,
     ip: i32,
@@ -109,16 +112,17 @@ pub fn part1() -> i32 {
 
 pub fn part2() -> i32 {
     let target_output = 19690720;
-    let initial = initial_state();
-
-    for noun in 0..=99 {
-        for verb in 0..=99 {
-            let mut computer = Computer::new(initial.clone());
-            computer.restore_state(noun, verb);
-            computer.compute();
-            if computer.result() == target_output {
-                return 100 * noun + verb;
-            }
+    let program = initial_state().into_iter().map(Intcode::from).collect();
+
+    let mut executor = SymbolicExecutor::new(program);
+    let noun = executor.memory_symbol(ADDR_NOUN as usize, "noun", 0..=99);
+    let verb = executor.memory_symbol(ADDR_VERB as usize, "verb", 0..=99);
+
+    for path in executor.explore() {
+        assert_eq!(PathEnd::Halted, path.end);
+        let goal = Expr::equal(path.read(0), Expr::constant(target_output));
+        if let Some(solution) = executor.solve_path(&path, Constraint::new(goal, true)) {
+            return (100 * solution[noun] + solution[verb]) as i32;
         }
     }
     panic!("Not found");
@@ -145,6 +149,47 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_symbolic_formula() {
+        let program = vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+        let mut executor = SymbolicExecutor::new(program);
+        executor.memory_symbol(9, "a", 0..=99);
+        executor.memory_symbol(10, "b", 0..=99);
+
+        let paths = executor.explore();
+        assert_eq!(1, paths.len());
+        assert_eq!("50*a + 50*b", paths[0].read(0).render(&executor.names()));
+    }
+
+    #[test]
+    fn test_symbolic_branches() {
+        // outputs 0 if the input was 0, otherwise 1
+        let program = vec![3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9];
+        let mut executor = SymbolicExecutor::new(program);
+        let x = executor.input_symbol("x", -5..=5);
+
+        let paths = executor.explore();
+        assert_eq!(2, paths.len());
+
+        let solutions: Vec<_> = paths
+            .iter()
+            .filter_map(|path| {
+                let goal = Expr::equal(path.outputs[0].clone(), Expr::constant(1));
+                executor.solve_path(path, Constraint::new(goal, true))
+            })
+            .collect();
+        assert_eq!(vec![vec![-5]], solutions);
+
+        let zero = paths
+            .iter()
+            .find_map(|path| {
+                let goal = Expr::equal(path.outputs[0].clone(), Expr::constant(0));
+                executor.solve_path(path, Constraint::new(goal, true))
+            })
+            .unwrap();
+        assert_eq!(0, zero[x]);
+    }
+
     #[test]
     fn run_part1() {
         assert_eq!(6327510, part1());
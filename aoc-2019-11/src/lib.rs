@@ -3,12 +3,15 @@
 #![allow(dead_code)]
 
 mod computer;
+mod droid;
 use computer::Computer;
 use computer::Intcode;
+use droid::{Codec, Droid, IntcodeDroid};
 
 type AdventResult = usize;
 
 use std::collections::HashMap;
+use std::collections::VecDeque;
 use std::fs;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -49,21 +52,6 @@ impl Position {
     }
 }
 
-enum InputState {
-    Paint,
-    Turn,
-}
-use InputState::*;
-
-impl InputState {
-    fn next(&self) -> Self {
-        match self {
-            Paint => Turn,
-            Turn => Paint,
-        }
-    }
-}
-
 #[derive(Debug)]
 enum TurnDirection {
     Left,
@@ -105,13 +93,39 @@ impl Orientation {
     }
 }
 
+/// What the robot does with the panel it's over.
+#[derive(Debug)]
+struct PaintInstruction {
+    paint: PaintColor,
+    turn: TurnDirection,
+}
 
+/// The robot is shown the color of the panel it's over, and answers
+/// with a color to paint it and which way to turn.
+struct PainterCodec;
+
+impl Codec for PainterCodec {
+    type Command = PaintColor;
+    type Reading = PaintInstruction;
+
+    fn encode(&self, color: &PaintColor) -> Vec<Intcode> {
+        vec![color.to_code()]
+    }
+
+    fn decode(&self, outputs: &mut VecDeque<Intcode>) -> Option<PaintInstruction> {
+        if outputs.len() < 2 {
+            return None;
+        }
+        let paint = PaintColor::from_code(outputs.pop_front().unwrap());
+        let turn = TurnDirection::from_code(outputs.pop_front().unwrap());
+        Some(PaintInstruction { paint, turn })
+    }
+}
 
 struct Robot {
     panels: HashMap<Position, PaintColor>,
-    computer: Computer,
+    droid: IntcodeDroid<PainterCodec>,
     position: Position,
-    input_state: InputState,
     facing: Orientation,
 }
 
@@ -119,9 +133,8 @@ impl Robot {
     fn new(program: Vec<Intcode>) -> Self {
         Robot {
             panels: HashMap::new(),
-            computer: Computer::new(program),
+            droid: IntcodeDroid::new(program, PainterCodec),
             position: Position(0, 0),
-            input_state: InputState::Paint,
             facing: North,
         }
     }
@@ -166,17 +179,11 @@ impl Robot {
     }
 
     fn run(&mut self) {
-        while !self.computer.is_halted() {
-            self.computer.start_or_resume();
-            while let Some(output) = self.computer.consume_output() {
-                match self.input_state {
-                    Paint => self.paint_panel(PaintColor::from_code(output)),
-                    Turn => self.turn(TurnDirection::from_code(output)),
-                }
-                self.input_state = self.input_state.next();
-            }
-            if self.computer.is_awaiting_input() {
-                self.computer.buffer_input(self.get_panel_color(self.position).to_code());
+        while !self.droid.is_halted() {
+            let color = self.get_panel_color(self.position);
+            for PaintInstruction { paint, turn } in self.droid.send(color) {
+                self.paint_panel(paint);
+                self.turn(turn);
             }
         }
     }
@@ -0,0 +1,85 @@
+#![allow(dead_code)]
+
+use crate::computer::Intcode;
+
+/// How a cell must compare against the previous scan to stay a
+/// candidate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Equals(Intcode),
+    IncreasedBy(Intcode),
+    DecreasedBy(Intcode),
+    Increased,
+    Decreased,
+    Changed,
+    Unchanged,
+}
+
+impl Filter {
+    /// Parses the scanner's command syntax: `= n`, `+ n`, `- n`, `+`,
+    /// `-`, `!` (changed) or `.` (unchanged).
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut words = s.split_whitespace();
+        let op = words.next()?;
+        let arg = words.next().map(|w| w.parse::<Intcode>());
+        if words.next().is_some() {
+            return None;
+        }
+
+        Some(match (op, arg) {
+            ("=", Some(Ok(n))) => Filter::Equals(n),
+            ("+", Some(Ok(n))) => Filter::IncreasedBy(n),
+            ("-", Some(Ok(n))) => Filter::DecreasedBy(n),
+            ("+", None) => Filter::Increased,
+            ("-", None) => Filter::Decreased,
+            ("!", None) => Filter::Changed,
+            (".", None) => Filter::Unchanged,
+            _ => return None,
+        })
+    }
+
+    fn matches(&self, old: Intcode, new: Intcode) -> bool {
+        match *self {
+            Filter::Equals(n) => new == n,
+            Filter::IncreasedBy(n) => new.checked_sub(old) == Some(n),
+            Filter::DecreasedBy(n) => old.checked_sub(new) == Some(n),
+            Filter::Increased => new > old,
+            Filter::Decreased => new < old,
+            Filter::Changed => new != old,
+            Filter::Unchanged => new == old,
+        }
+    }
+}
+
+/// Narrows down the addresses that could hold some value of interest
+/// by filtering them against successive snapshots of memory.
+pub struct Scanner {
+    candidates: Vec<usize>,
+    last: Vec<Intcode>,
+}
+
+impl Scanner {
+    /// Starts with every address in `memory` as a candidate.
+    pub fn new(memory: &[Intcode]) -> Self {
+        Scanner {
+            candidates: (0..memory.len()).collect(),
+            last: memory.to_vec(),
+        }
+    }
+
+    pub fn candidates(&self) -> &[usize] {
+        &self.candidates
+    }
+
+    /// Keeps the candidates whose value in `memory` passes `filter`
+    /// against the previous scan, and returns how many remain.
+    pub fn scan(&mut self, memory: &[Intcode], filter: Filter) -> usize {
+        let cell = |m: &[Intcode], addr: usize| m.get(addr).copied().unwrap_or(0);
+
+        let last = &self.last;
+        self.candidates
+            .retain(|&addr| filter.matches(cell(last, addr), cell(memory, addr)));
+        self.last = memory.to_vec();
+        self.candidates.len()
+    }
+}
@@ -1,6 +1,6 @@
 #![allow(dead_code)]
 
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
 use std::mem::swap;
 
 pub type Intcode = i64;
@@ -92,6 +92,7 @@ pub struct Computer {
     outputs: VecDeque<i64>,
     op: Option<Op>,
     relative_base: i64,
+    frozen: BTreeMap<i64, i64>,
 }
 
 impl Computer {
@@ -108,6 +109,7 @@ impl Computer {
             outputs: VecDeque::new(),
             op: None,
             relative_base: 0,
+            frozen: BTreeMap::new(),
         }
     }
 
@@ -116,6 +118,13 @@ impl Computer {
         self.relative_base += a;
     }
 
+    fn apply_frozen(&mut self) {
+        // freeze() has already grown memory to cover every frozen cell
+        for (&p, &n) in &self.frozen {
+            self.memory[p as usize] = n;
+        }
+    }
+
     fn binary_op<F>(&mut self, pa: Parameter, pb: Parameter, pc: Parameter, f: F)
     where
         F: FnOnce(i64, i64) -> i64,
@@ -139,6 +148,7 @@ impl Computer {
         while self.state == ComputerState::Running {
             self.read_next_instruction();
             self.execute();
+            self.apply_frozen();
         }
     }
 
@@ -184,6 +194,18 @@ impl Computer {
         outputs.into_iter()
     }
 
+    /// Pins the cell at `p` to `n`. The value is written immediately
+    /// and restored after every instruction until unfrozen.
+    pub fn freeze(&mut self, p: i64, n: i64) {
+        assert!(p >= 0);
+        self.write(p, n);
+        self.frozen.insert(p, n);
+    }
+
+    pub fn frozen(&self) -> impl Iterator<Item = (i64, i64)> + '_ {
+        self.frozen.iter().map(|(&p, &n)| (p, n))
+    }
+
     pub fn is_awaiting_input(&self) -> bool {
         ComputerState::AwaitingInput == self.state
     }
@@ -192,6 +214,12 @@ impl Computer {
         ComputerState::Halted == self.state
     }
 
+    /// The machine's memory as it stands, including any cells the
+    /// program has grown into.
+    pub fn memory(&self) -> &[Intcode] {
+        &self.memory
+    }
+
     fn jump_if_false(&mut self, pa: Parameter, pb: Parameter) {
         let cond = self.deref(&pa);
         if cond == 0 {
@@ -296,6 +324,7 @@ impl Computer {
 
         self.state = ComputerState::Running;
         self.execute();
+        self.apply_frozen();
 
         self.compute();
     }
@@ -328,6 +357,10 @@ impl Computer {
         }
     }
 
+    pub fn unfreeze(&mut self, p: i64) {
+        self.frozen.remove(&p);
+    }
+
     pub fn write(&mut self, p: i64, n: i64) {
         assert!(p >= 0);
         while self.memory.len() - 1 < p as usize {
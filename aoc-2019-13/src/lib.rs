@@ -1,6 +1,8 @@
 // -*- compile-command: "cargo test -- --show-output" -*-
 
+mod cheat;
 mod computer;
+mod memory;
 
 type AdventResult = usize;
 
@@ -142,6 +144,12 @@ pub fn part2() -> Intcode {
 #[cfg(test)]
 mod test {
     use super::*;
+    use cheat::{Filter, Scanner};
+
+    // Found with the memory diff and cheat scanner.
+    const PADDLE_X: usize = 392;
+    const SCREEN: Intcode = 639;
+    const BOARD_WIDTH: Intcode = 42;
 
     // no examples
 
@@ -154,4 +162,89 @@ mod test {
     fn part2_solution() {
         assert_eq!(13_140, part2());
     }
+
+    #[test]
+    fn cheat_scan_finds_paddle() {
+        let mut computer = Computer::new(Computer::parse_program(&input()));
+        computer.write(0, 2);
+        computer.start();
+        let mut board = Board::new();
+        board.import(computer.consume_output_buffer());
+
+        let mut scanner = Scanner::new(computer.memory());
+        scanner.scan(computer.memory(), Filter::Equals(board.paddle.0));
+        for (joystick, filter) in [
+            (1, Filter::IncreasedBy(1)),
+            (0, Filter::Unchanged),
+            (-1, Filter::DecreasedBy(1)),
+        ] {
+            computer.buffer_input(joystick);
+            computer.resume();
+            scanner.scan(computer.memory(), filter);
+        }
+        assert_eq!(&[PADDLE_X], scanner.candidates());
+    }
+
+    #[test]
+    fn cheat_freeze_paddle_row() {
+        // Pinning the whole paddle row to paddle tiles means the ball
+        // can't get past with the joystick left alone.
+        let mut computer = Computer::new(Computer::parse_program(&input()));
+        computer.write(0, 2);
+        for x in 1..BOARD_WIDTH - 1 {
+            computer.freeze(SCREEN + 22 * BOARD_WIDTH + x, 3);
+        }
+
+        let mut board = Board::new();
+        while !computer.is_halted() {
+            computer.start_or_resume();
+            board.import(computer.consume_output_buffer());
+            computer.buffer_input(0);
+        }
+
+        assert_eq!(0, board.tile_count(Tile::Block));
+        assert_eq!(13_140, board.score);
+    }
+
+    #[test]
+    fn memory_dump_rows() {
+        let expected = ["0:   1 -20 300", "3:   4   5"];
+        assert_eq!(
+            expected.map(|line| format!("{}\n", line)).concat(),
+            memory::dump(&[1, -20, 300, 4, 5], 3)
+        );
+    }
+
+    #[test]
+    fn memory_diff_regions() {
+        let regions = memory::diff(&[1, 2, 3, 4, 5], &[1, 9, 9, 4, 6, 7]);
+        assert_eq!(
+            "@ 1..3\n  1: 2 -> 9\n  2: 3 -> 9\n@ 4..6\n  4: 5 -> 6\n  5: 0 -> 7\n",
+            memory::format_diff(&regions)
+        );
+        assert!(memory::diff(&[1, 2], &[1, 2, 0]).is_empty());
+    }
+
+    #[test]
+    fn memory_diff_finds_ball_and_paddle() {
+        let mut computer = Computer::new(Computer::parse_program(&input()));
+        computer.write(0, 2);
+        computer.start();
+        computer.consume_output_buffer().for_each(drop);
+
+        let before = computer.memory().to_vec();
+        computer.buffer_input(1);
+        computer.resume();
+        let mut board = Board::new();
+        board.import(computer.consume_output_buffer());
+
+        let changes: HashMap<usize, Intcode> = memory::diff(&before, computer.memory())
+            .into_iter()
+            .flat_map(|r| r.changes)
+            .map(|c| (c.addr, c.new))
+            .collect();
+        assert_eq!(Some(&board.ball.0), changes.get(&388));
+        assert_eq!(Some(&board.ball.1), changes.get(&389));
+        assert_eq!(Some(&board.paddle.0), changes.get(&PADDLE_X));
+    }
 }
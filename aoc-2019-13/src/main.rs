@@ -0,0 +1,140 @@
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+mod cheat;
+mod computer;
+mod memory;
+
+use cheat::{Filter, Scanner};
+
+use computer::{Computer, Intcode};
+
+const USAGE: &str = "\
+usage: aoc-2019-13 dump [cols]
+       aoc-2019-13 diff <joystick>...
+       aoc-2019-13 scan
+
+All commands put the arcade in free play and run it up to its first
+request for joystick input. `dump` prints memory at that point; `diff`
+then feeds each joystick position (-1, 0 or 1) in turn and prints what
+changed in memory after each one. `scan` reads cheat commands from
+stdin:
+
+  in <n>          feed joystick position n and run to the next frame
+  = n | + n | - n keep addresses now equal to n, or up or down by n
+  + | - | ! | .   keep addresses that went up, down, changed or didn't
+  list            show the remaining addresses and their values
+  reset           start over with every address
+  freeze <a> <n>  pin address a to n after every instruction
+  unfreeze <a>    release address a";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+fn arcade() -> Computer {
+    let program = fs::read_to_string("input.txt").expect("Can't find input.txt");
+    let mut computer = Computer::new(Computer::parse_program(&program));
+    computer.write(0, 2);
+    computer.start();
+    computer.consume_output_buffer().for_each(drop);
+    computer
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args[..] {
+        ["dump"] => print!("{}", memory::dump(arcade().memory(), 16)),
+        ["dump", cols] => match cols.parse() {
+            Ok(cols) if cols > 0 => print!("{}", memory::dump(arcade().memory(), cols)),
+            _ => usage(),
+        },
+        ["diff", ref inputs @ ..] if !inputs.is_empty() => {
+            let inputs: Vec<Intcode> = inputs
+                .iter()
+                .map(|s| s.parse().unwrap_or_else(|_| usage()))
+                .collect();
+
+            let mut computer = arcade();
+            for input in inputs {
+                let before = computer.memory().to_vec();
+                computer.buffer_input(input);
+                computer.resume();
+                let outputs: Vec<_> = computer.consume_output_buffer().collect();
+
+                println!("== joystick {} ({} outputs)", input, outputs.len());
+                print!(
+                    "{}",
+                    memory::format_diff(&memory::diff(&before, computer.memory()))
+                );
+                if computer.is_halted() {
+                    println!("== halted");
+                    break;
+                }
+            }
+        }
+        ["scan"] => scan(arcade()),
+        _ => usage(),
+    }
+}
+
+fn scan(mut computer: Computer) {
+    let mut scanner = Scanner::new(computer.memory());
+
+    print!("> ");
+    io::stdout().flush().unwrap();
+    for line in io::stdin().lock().lines() {
+        let line = line.unwrap();
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        match words[..] {
+            [] => (),
+            ["in", n] if !computer.is_halted() => match n.parse() {
+                Ok(n) => {
+                    computer.buffer_input(n);
+                    computer.resume();
+                    computer.consume_output_buffer().for_each(drop);
+                    if computer.is_halted() {
+                        println!("halted");
+                    }
+                }
+                Err(_) => println!("bad joystick position {}", n),
+            },
+            ["in", _] => println!("halted"),
+            ["list"] => {
+                for &addr in scanner.candidates().iter().take(32) {
+                    let value = computer.memory().get(addr).copied().unwrap_or(0);
+                    println!("{}: {}", addr, value);
+                }
+                if scanner.candidates().len() > 32 {
+                    println!("... {} more", scanner.candidates().len() - 32);
+                }
+            }
+            ["reset"] => scanner = Scanner::new(computer.memory()),
+            ["freeze", a, n] => match (a.parse(), n.parse()) {
+                (Ok(a), Ok(n)) if a >= 0 => computer.freeze(a, n),
+                _ => println!("bad freeze {} {}", a, n),
+            },
+            ["unfreeze", a] => match a.parse() {
+                Ok(a) => computer.unfreeze(a),
+                _ => println!("bad address {}", a),
+            },
+            _ => match Filter::parse(&line) {
+                Some(filter) => {
+                    let left = scanner.scan(computer.memory(), filter);
+                    println!("{} candidates", left);
+                }
+                None => println!("unknown command {}", line.trim()),
+            },
+        }
+
+        print!("> ");
+        io::stdout().flush().unwrap();
+    }
+    println!();
+}
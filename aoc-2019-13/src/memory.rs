@@ -0,0 +1,93 @@
+#![allow(dead_code)]
+
+use crate::computer::Intcode;
+
+use std::fmt;
+
+/// Renders memory as aligned rows of `cols` cells, each row prefixed
+/// with the address of its first cell.
+pub fn dump(memory: &[Intcode], cols: usize) -> String {
+    assert!(cols > 0);
+
+    let addr_width = memory.len().saturating_sub(1).to_string().len();
+    let cell_width = memory
+        .iter()
+        .map(|n| n.to_string().len())
+        .max()
+        .unwrap_or(1);
+
+    let mut sb = String::new();
+    for (row, cells) in memory.chunks(cols).enumerate() {
+        sb += &format!("{:>addr_width$}:", row * cols);
+        for n in cells {
+            sb += &format!(" {:>cell_width$}", n);
+        }
+        sb += "\n";
+    }
+    sb
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Change {
+    pub addr: usize,
+    pub old: Intcode,
+    pub new: Intcode,
+}
+
+/// A run of changed cells at consecutive addresses.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Region {
+    pub changes: Vec<Change>,
+}
+
+impl Region {
+    pub fn start(&self) -> usize {
+        self.changes[0].addr
+    }
+
+    pub fn end(&self) -> usize {
+        self.changes.last().unwrap().addr + 1
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "@ {}..{}", self.start(), self.end())?;
+        for c in &self.changes {
+            writeln!(f, "  {}: {} -> {}", c.addr, c.old, c.new)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares two snapshots of memory. Cells past the end of the shorter
+/// snapshot count as 0, which is what the machine would read there.
+pub fn diff(old: &[Intcode], new: &[Intcode]) -> Vec<Region> {
+    let mut regions: Vec<Region> = vec![];
+
+    for addr in 0..old.len().max(new.len()) {
+        let o = old.get(addr).copied().unwrap_or(0);
+        let n = new.get(addr).copied().unwrap_or(0);
+        if o == n {
+            continue;
+        }
+
+        let change = Change {
+            addr,
+            old: o,
+            new: n,
+        };
+        match regions.last_mut() {
+            Some(region) if region.end() == addr => region.changes.push(change),
+            _ => regions.push(Region {
+                changes: vec![change],
+            }),
+        }
+    }
+
+    regions
+}
+
+pub fn format_diff(regions: &[Region]) -> String {
+    regions.iter().map(|r| r.to_string()).collect()
+}
@@ -0,0 +1,94 @@
+//! Typed commands and sensor readings for the Intcode droids.
+//!
+//! Each puzzle's droid speaks its own protocol over the computer's
+//! inputs and outputs. A `Codec` translates it, and `IntcodeDroid`
+//! drives a computer through one, so the rest of the code only deals
+//! in commands and readings. The same file is shared by every day with
+//! a droid in it.
+
+use std::collections::VecDeque;
+
+use crate::computer::{Computer, Intcode};
+
+/// Translates a droid's commands and readings to and from Intcodes.
+pub trait Codec {
+    type Command;
+    type Reading;
+
+    fn encode(&self, command: &Self::Command) -> Vec<Intcode>;
+
+    /// Takes one reading off the front of `outputs`, if all of it has
+    /// arrived.
+    fn decode(&self, outputs: &mut VecDeque<Intcode>) -> Option<Self::Reading>;
+}
+
+pub trait Droid {
+    type Command;
+    type Reading;
+
+    /// Gives the droid `command` and lets it run until it wants
+    /// another, returning what it reported meanwhile.
+    fn send(&mut self, command: Self::Command) -> Vec<Self::Reading>;
+
+    fn is_halted(&self) -> bool;
+}
+
+/// A droid run by an Intcode program.
+pub struct IntcodeDroid<C: Codec> {
+    computer: Computer,
+    codec: C,
+    /// Outputs that aren't a whole reading yet.
+    outputs: VecDeque<Intcode>,
+}
+
+impl<C: Codec> IntcodeDroid<C> {
+    pub fn new(program: Vec<Intcode>, codec: C) -> Self {
+        IntcodeDroid {
+            computer: Computer::new(program),
+            codec,
+            outputs: VecDeque::new(),
+        }
+    }
+
+    /// The computer underneath, for setting it up before the droid
+    /// starts.
+    pub fn computer_mut(&mut self) -> &mut Computer {
+        &mut self.computer
+    }
+
+    /// Starts the droid without a command, returning what it reports
+    /// before it wants one.
+    pub fn start(&mut self) -> Vec<C::Reading> {
+        self.computer.start();
+        self.readings()
+    }
+
+    fn readings(&mut self) -> Vec<C::Reading> {
+        while let Some(n) = self.computer.consume_output() {
+            self.outputs.push_back(n);
+        }
+
+        let mut readings = vec![];
+        while let Some(reading) = self.codec.decode(&mut self.outputs) {
+            readings.push(reading);
+        }
+        readings
+    }
+}
+
+impl<C: Codec> Droid for IntcodeDroid<C> {
+    type Command = C::Command;
+    type Reading = C::Reading;
+
+    fn send(&mut self, command: Self::Command) -> Vec<Self::Reading> {
+        for n in self.codec.encode(&command) {
+            self.computer.buffer_input(n);
+        }
+        self.computer.start_or_resume();
+        self.readings()
+    }
+
+    fn is_halted(&self) -> bool {
+        self.computer.is_halted()
+    }
+}
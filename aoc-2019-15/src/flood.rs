@@ -0,0 +1,135 @@
+//! Breadth-first flood fill over a mapped maze.
+//!
+//! Oxygen spreads from the system to every open neighbour once a
+//! minute, which is just a breadth-first search with the frontier kept
+//! for each step. The same search answers how far any tile is from any
+//! other.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{Direction, Maze, Position, Tile};
+
+/// How far every reachable tile is from one tile.
+pub struct Distances {
+    pub from: Position,
+    distances: HashMap<Position, usize>,
+}
+
+impl Distances {
+    pub fn to(&self, p: Position) -> Option<usize> {
+        self.distances.get(&p).copied()
+    }
+
+    /// The distance to the furthest reachable tile.
+    pub fn max(&self) -> usize {
+        self.distances.values().copied().max().unwrap_or(0)
+    }
+}
+
+/// Oxygen filling the maze, minute by minute.
+pub struct Fill {
+    /// The tiles oxygen reaches in each minute, starting with the
+    /// system itself at minute 0.
+    pub frontiers: Vec<Vec<Position>>,
+}
+
+impl Fill {
+    /// How long it takes to fill the whole maze.
+    pub fn minutes(&self) -> usize {
+        self.frontiers.len() - 1
+    }
+
+    /// The maze at the end of each minute, with the oxygen drawn as
+    /// the system is.
+    pub fn frames(&self, maze: &Maze) -> Vec<String> {
+        let mut oxygen = HashSet::new();
+        self.frontiers
+            .iter()
+            .map(|frontier| {
+                oxygen.extend(frontier.iter().copied());
+                maze.render(&oxygen)
+            })
+            .collect()
+    }
+}
+
+fn is_open(maze: &Maze, p: Position) -> bool {
+    matches!(maze.map.get(&p), Some(t) if *t != Tile::Wall)
+}
+
+/// Searches out from `from`, returning each step's frontier.
+fn frontiers(maze: &Maze, from: Position) -> Vec<Vec<Position>> {
+    let mut seen = HashSet::from([from]);
+    let mut frontiers = vec![vec![from]];
+
+    loop {
+        let mut next = vec![];
+        for &p in frontiers.last().unwrap() {
+            for d in Direction::ALL {
+                let q = d.of(p);
+                if is_open(maze, q) && seen.insert(q) {
+                    next.push(q);
+                }
+            }
+        }
+        if next.is_empty() {
+            return frontiers;
+        }
+        frontiers.push(next);
+    }
+}
+
+pub fn distances(maze: &Maze, from: Position) -> Distances {
+    let distances = frontiers(maze, from)
+        .into_iter()
+        .enumerate()
+        .flat_map(|(d, frontier)| frontier.into_iter().map(move |p| (p, d)))
+        .collect();
+    Distances { from, distances }
+}
+
+/// Fills the maze from its oxygen system.
+pub fn fill(maze: &Maze) -> Fill {
+    Fill {
+        frontiers: frontiers(maze, maze.goal),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::mapfile;
+
+    // from the puzzle text
+    const EXAMPLE: &str = " ##\n#..##\n#.#..#\n#.O.#\n ###\n";
+
+    fn example() -> Maze {
+        Maze::new(mapfile::from_text(EXAMPLE).unwrap())
+    }
+
+    #[test]
+    fn fill_example() {
+        let maze = example();
+        let fill = fill(&maze);
+        assert_eq!(4, fill.minutes());
+        assert_eq!(vec![Position(2, 3)], fill.frontiers[0]);
+        assert_eq!(vec![Position(2, 1)], fill.frontiers[4]);
+
+        let frames = fill.frames(&maze);
+        assert_eq!(5, frames.len());
+        assert_eq!("•▓▓•••\n▓  ▓▓•\n▓ ▓  ▓\n▓ X ▓•\n•▓▓▓••", frames[0]);
+        assert_eq!("•▓▓•••\n▓XX▓▓•\n▓X▓XX▓\n▓XXX▓•\n•▓▓▓••", frames[4]);
+    }
+
+    #[test]
+    fn distance_queries() {
+        let maze = example();
+        let d = distances(&maze, Position(1, 1));
+        assert_eq!(Some(0), d.to(Position(1, 1)));
+        assert_eq!(Some(3), d.to(Position(2, 3)));
+        assert_eq!(Some(6), d.to(Position(4, 2)));
+        assert_eq!(None, d.to(Position(0, 0)));
+        assert_eq!(6, d.max());
+    }
+}
@@ -3,16 +3,22 @@
 type AdventResult = usize;
 
 mod computer;
+mod droid;
+mod flood;
+mod mapfile;
 
 use computer::Computer;
 use computer::Intcode;
+use droid::{Codec, Droid, IntcodeDroid};
 
-use std::cmp::Ordering;
-use std::collections::BinaryHeap;
-use std::collections::HashMap;
-use std::collections::HashSet;
+use std::collections::{hash_map::DefaultHasher, HashMap, HashSet, VecDeque};
 use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
 use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 type Coordinate = i32;
 type CoordinateDistance = u32;
@@ -21,10 +27,6 @@ type CoordinateDistance = u32;
 struct Position(Coordinate, Coordinate);
 
 impl Position {
-    fn manhattan(&self, other: &Position) -> u32 {
-        (self.0 - other.0).abs() as u32 + (self.1 - other.1).abs() as u32
-    }
-
     fn max(&self, other: &Position) -> Position {
         Position(self.0.max(other.0), self.1.max(other.1))
     }
@@ -34,69 +36,6 @@ impl Position {
     }
 }
 
-#[derive(Clone)]
-struct PartialPath<'a> {
-    path: Vec<Position>,
-    maze: &'a Maze,
-}
-
-impl<'a> PartialEq for PartialPath<'a> {
-    fn eq(&self, other: &Self) -> bool {
-        self.path == other.path
-    }
-}
-
-impl<'a> Eq for PartialPath<'a> {}
-
-impl<'a> PartialOrd for PartialPath<'a> {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-impl<'a> Ord for PartialPath<'a> {
-    // compares opposite natural ordering because lower cost = higher
-    // priority for the queue
-    fn cmp(&self, other: &Self) -> Ordering {
-        other.min_cost_to_goal().cmp(&self.min_cost_to_goal())
-    }
-}
-
-impl<'a> PartialPath<'a> {
-    fn new(maze: &'a Maze, pos: Position) -> Self {
-        let path = vec![pos];
-        PartialPath { path, maze }
-    }
-
-    fn branch(&self, p: Position) -> Self {
-        let mut path = self.path.clone();
-        path.push(p);
-        PartialPath {
-            path,
-            maze: self.maze,
-        }
-    }
-
-    fn cost(&self) -> usize {
-        self.len() - 1
-    }
-
-    fn min_cost_to_goal(&self) -> u32 {
-        let p = self.path.last().unwrap();
-        self.cost() as u32 + p.manhattan(&self.maze.goal)
-    }
-}
-
-use std::ops::Deref;
-
-impl<'a> Deref for PartialPath<'a> {
-    type Target = Vec<Position>;
-
-    fn deref(&self) -> &Self::Target {
-        &self.path
-    }
-}
-
 #[derive(Clone, Copy)]
 enum Direction {
     North,
@@ -158,6 +97,23 @@ impl Tile {
     }
 }
 
+/// The repair droid takes a direction to move in and reports what it
+/// found there.
+struct RepairCodec;
+
+impl Codec for RepairCodec {
+    type Command = Direction;
+    type Reading = Tile;
+
+    fn encode(&self, d: &Direction) -> Vec<Intcode> {
+        vec![d.code()]
+    }
+
+    fn decode(&self, outputs: &mut VecDeque<Intcode>) -> Option<Tile> {
+        outputs.pop_front().map(Tile::from_result)
+    }
+}
+
 struct Maze {
     goal: Position,
     map: HashMap<Position, Tile>,
@@ -167,7 +123,7 @@ struct Maze {
 
 impl Maze {
     fn new(map: HashMap<Position, Tile>) -> Self {
-        assert!(map.len() > 0);
+        assert!(!map.is_empty());
 
         let mut pos_min = Position(Coordinate::MAX, Coordinate::MAX);
         let mut pos_max = Position(Coordinate::MIN, Coordinate::MIN);
@@ -193,14 +149,26 @@ impl Maze {
     }
 
     fn display(&self) {
+        println!("{}", self.render(&HashSet::new()));
+    }
+
+    /// Draws the maze, with any tiles in `oxygen` drawn as the oxygen
+    /// system is.
+    fn render(&self, oxygen: &HashSet<Position>) -> String {
         let mut sb = String::new();
 
         for y in self.yrange() {
-            if sb.len() > 0 {
+            if !sb.is_empty() {
                 sb += "\n";
             }
             for x in self.xrange() {
-                sb += match self.map.get(&Position(x, y)) {
+                let pos = Position(x, y);
+                let tile = if oxygen.contains(&pos) {
+                    Some(&Oxygen)
+                } else {
+                    self.map.get(&pos)
+                };
+                sb += match tile {
                     None => "•",
                     Some(Origin) => "*",
                     Some(Empty) => " ",
@@ -210,33 +178,13 @@ impl Maze {
             }
         }
 
-        println!("{}", sb);
+        sb
     }
 
     fn shortest_path(&self) -> usize {
-        self.shortest_path_from(Position(0, 0)).cost()
-    }
-
-    fn shortest_path_from(&self, p: Position) -> PartialPath {
-        let mut paths = BinaryHeap::new();
-        paths.push(PartialPath::new(self, p));
-
-        while let Some(path) = paths.pop() {
-            let pos = *path.last().unwrap();
-            for d in Direction::ALL {
-                let next = d.of(pos);
-                if path.contains(&next) {
-                    continue;
-                }
-
-                match self.map.get(&next).unwrap() {
-                    Oxygen => return path.branch(next),
-                    Wall => continue,
-                    Empty | Origin => paths.push(path.branch(next)),
-                }
-            }
-        }
-        panic!("expected to find a path to goal");
+        flood::distances(self, Position(0, 0))
+            .to(self.goal)
+            .expect("expected to find a path to goal")
     }
 
     fn xrange(&self) -> RangeInclusive<Coordinate> {
@@ -249,7 +197,7 @@ impl Maze {
 }
 
 struct MazeMapper {
-    computer: Computer,
+    droid: IntcodeDroid<RepairCodec>,
     map: HashMap<Position, Tile>,
     position: Position,
 }
@@ -257,12 +205,12 @@ struct MazeMapper {
 impl MazeMapper {
     fn build_maze(input: &str) -> Maze {
         let program = Computer::parse_program(input);
-        let computer = Computer::new(program);
+        let droid = IntcodeDroid::new(program, RepairCodec);
         let mut map = HashMap::new();
         map.insert(Position(0, 0), Origin);
 
         let mut mapper = MazeMapper {
-            computer,
+            droid,
             map,
             position: Position(0, 0),
         };
@@ -270,45 +218,84 @@ impl MazeMapper {
         Maze::new(mapper.map)
     }
 
-    fn explore(&mut self) {
-        for d in Direction::ALL {
-            if !self.map.contains_key(&d.of(self.position)) {
-                self.venture(d);
-            }
+    /// Where the maze for `input` is cached in `dir`. The name carries
+    /// a hash of the program, so a changed input never picks up a
+    /// stale map. The hash may change between Rust releases, which
+    /// only costs exploring once more.
+    fn cache_path(input: &str, dir: &Path) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        input.trim().hash(&mut hasher);
+        dir.join(format!("aoc-2019-15-maze-{:016x}.txt", hasher.finish()))
+    }
+
+    /// The maze cached in `dir` for `input`, or if there isn't a
+    /// usable one, the maze explored afresh and cached there. The cache
+    /// only ever saves time: a map that won't load is replaced, and one
+    /// that can't be saved is just explored again next time.
+    fn load_or_build(input: &str, dir: &Path) -> Maze {
+        let path = Self::cache_path(input, dir);
+        if let Ok(map) = mapfile::load(&path) {
+            return Maze::new(map);
         }
+
+        let maze = Self::build_maze(input);
+        let _ = Self::save_cached(&path, &maze);
+        maze
+    }
+
+    /// Saves under a name of our own and moves it into place, so anyone
+    /// else loading the cache never sees half a map.
+    fn save_cached(path: &Path, maze: &Maze) -> io::Result<()> {
+        static SAVES: AtomicUsize = AtomicUsize::new(0);
+
+        let n = SAVES.fetch_add(1, Ordering::Relaxed);
+        let partial = path.with_extension(format!("{}-{}.tmp", process::id(), n));
+        let saved = mapfile::save(&partial, &maze.map).and_then(|()| fs::rename(&partial, path));
+        if saved.is_err() {
+            let _ = fs::remove_file(&partial);
+        }
+        saved
     }
 
-    fn venture(&mut self, d: Direction) {
-        let origin = self.position;
-        let dest = d.of(origin);
+    /// Explores depth-first, keeping the way back on a stack of its
+    /// own so a deep maze can't overflow ours.
+    fn explore(&mut self) {
+        let mut trail = vec![];
 
-        // Try moving to the destination
-        self.computer.buffer_input(d.code());
-        self.computer.start_or_resume();
+        loop {
+            let unexplored = Direction::ALL
+                .into_iter()
+                .find(|d| !self.map.contains_key(&d.of(self.position)));
 
-        // Record the contents of the destination
-        let result = self.computer.consume_output().expect("should get output");
-        let tile = Tile::from_result(result);
-        self.map.insert(dest, tile);
+            if let Some(d) = unexplored {
+                if self.step(d) {
+                    trail.push(d);
+                }
+            } else if let Some(d) = trail.pop() {
+                // Nothing left to see here, so head back
+                assert!(self.step(d.reverse()));
+            } else {
+                break;
+            }
+        }
+    }
 
-        // In the case of a Wall, we did not actually move. We're done.
+    /// Tries to move the droid, recording what it finds, and returns
+    /// whether it moved.
+    fn step(&mut self, d: Direction) -> bool {
+        let [tile] = self.droid.send(d)[..] else {
+            panic!("should get one status");
+        };
+        let dest = d.of(self.position);
+        self.map.entry(dest).or_insert(tile);
+
+        // In the case of a Wall, we did not actually move.
         if tile == Wall {
-            return;
+            return false;
         }
 
-        // Otherwise, we did successfully move. Update our position
-        // and recursively explore
         self.position = dest;
-        self.explore();
-
-        // Complete the venture by reversing the movement
-        self.computer.buffer_input(d.reverse().code());
-        self.computer.start_or_resume();
-        assert_ne!(
-            Wall,
-            Tile::from_result(self.computer.consume_output().unwrap())
-        );
-        self.position = origin;
+        true
     }
 }
 
@@ -317,34 +304,13 @@ fn input() -> String {
 }
 
 fn do_part1(input: &str) -> AdventResult {
-    let maze = MazeMapper::build_maze(input);
+    let maze = MazeMapper::load_or_build(input, &std::env::temp_dir());
     maze.shortest_path()
 }
 
 fn do_part2(input: &str) -> AdventResult {
-    let maze = MazeMapper::build_maze(input);
-
-    let mut seen = HashSet::new();
-
-    let mut longest = None;
-    for (pos, _) in maze.map.iter().filter(|(p, &t)| t == Empty) {
-        if seen.contains(pos) {
-            continue;
-        }
-
-        let len = maze.shortest_path_from(*pos);
-
-        for &p in len.iter() {
-            seen.insert(p);
-        }
-
-        longest = match longest {
-            None => Some(len),
-            Some(x) if len.cost() > x.cost() => Some(len),
-            x => x,
-        }
-    }
-    longest.unwrap().cost()
+    let maze = MazeMapper::load_or_build(input, &std::env::temp_dir());
+    flood::fill(&maze).minutes()
 }
 
 fn part1() -> AdventResult {
@@ -368,4 +334,51 @@ mod test {
     fn part2_solution() {
         assert_eq!(312, part2());
     }
+
+    #[test]
+    fn repair_droid() {
+        let maze = mapfile::load("maze.txt").unwrap();
+        let mut droid = IntcodeDroid::new(Computer::parse_program(&input()), RepairCodec);
+        for d in Direction::ALL {
+            let tile = maze[&d.of(Position(0, 0))];
+            assert_eq!(vec![tile], droid.send(d));
+            if tile != Wall {
+                assert_eq!(vec![Empty], droid.send(d.reverse()));
+            }
+        }
+    }
+
+    #[test]
+    fn explored_maze() {
+        // maze.txt is the explored maze, kept as a fixture
+        let maze = MazeMapper::build_maze(&input());
+        assert_eq!(mapfile::load("maze.txt").unwrap(), maze.map);
+
+        let dir = std::env::temp_dir().join(format!("aoc-2019-15-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let built = MazeMapper::load_or_build(&input(), &dir);
+        assert_eq!(maze.map, built.map);
+        assert!(MazeMapper::cache_path(&input(), &dir).exists());
+
+        // a stand-in map, cached as if some other program had made it
+        let other = "1,2,3";
+        let stand_in = mapfile::from_text("*.O\n").unwrap();
+        mapfile::save(MazeMapper::cache_path(other, &dir), &stand_in).unwrap();
+        assert_eq!(2, MazeMapper::load_or_build(other, &dir).shortest_path());
+        let cached = MazeMapper::load_or_build(&input(), &dir);
+        assert_eq!(300, cached.shortest_path());
+
+        // a corrupt map is explored again and replaced
+        let path = MazeMapper::cache_path(&input(), &dir);
+        fs::write(&path, "#?#\n").unwrap();
+        assert_eq!(maze.map, MazeMapper::load_or_build(&input(), &dir).map);
+        assert_eq!(maze.map, mapfile::load(&path).unwrap());
+
+        // and one that can't be saved is no reason to fail
+        let nowhere = dir.join("missing");
+        assert_eq!(maze.map, MazeMapper::load_or_build(&input(), &nowhere).map);
+        assert!(!nowhere.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,171 @@
+//! Saves a mapped maze as text and loads it back, so it needn't be
+//! explored through the droid every time.
+//!
+//! ```text
+//!  ##
+//! #..##
+//! #*#..#
+//! #.O.#
+//!  ###
+//! ```
+//!
+//! Walls are `#`, open tiles `.`, the droid's starting point `*` and
+//! the oxygen system `O`. Anything unexplored is a space. The starting
+//! point is at `(0, 0)`; a map without one starts at its top left.
+//! A map has at most one starting point and exactly one oxygen system.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::{Coordinate, Position, Tile};
+
+#[derive(Debug)]
+pub enum MapError {
+    Io(io::Error),
+    /// An unexpected character, at a line and column counted from 1.
+    BadTile(usize, usize, char),
+    /// A second starting point, at a line and column counted from 1.
+    ExtraOrigin(usize, usize),
+    /// A second oxygen system, at a line and column counted from 1.
+    ExtraOxygen(usize, usize),
+    NoOxygen,
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapError::Io(e) => write!(f, "{}", e),
+            MapError::BadTile(line, col, c) => {
+                write!(f, "line {}, column {}: unexpected {:?}", line, col, c)
+            }
+            MapError::ExtraOrigin(line, col) => {
+                write!(f, "line {}, column {}: second starting point", line, col)
+            }
+            MapError::ExtraOxygen(line, col) => {
+                write!(f, "line {}, column {}: second oxygen system", line, col)
+            }
+            MapError::NoOxygen => write!(f, "no oxygen system"),
+        }
+    }
+}
+
+impl std::error::Error for MapError {}
+
+impl From<io::Error> for MapError {
+    fn from(e: io::Error) -> Self {
+        MapError::Io(e)
+    }
+}
+
+fn glyph(tile: Option<&Tile>) -> char {
+    match tile {
+        None => ' ',
+        Some(Tile::Origin) => '*',
+        Some(Tile::Empty) => '.',
+        Some(Tile::Wall) => '#',
+        Some(Tile::Oxygen) => 'O',
+    }
+}
+
+pub fn to_text(map: &HashMap<Position, Tile>) -> String {
+    let xs = map.keys().map(|p| p.0);
+    let ys = map.keys().map(|p| p.1);
+    let (Some(xmin), Some(xmax)) = (xs.clone().min(), xs.max()) else {
+        return String::new();
+    };
+    let (ymin, ymax) = (ys.clone().min().unwrap(), ys.max().unwrap());
+
+    let mut sb = String::new();
+    for y in ymin..=ymax {
+        let line: String = (xmin..=xmax)
+            .map(|x| glyph(map.get(&Position(x, y))))
+            .collect();
+        sb += line.trim_end();
+        sb += "\n";
+    }
+    sb
+}
+
+pub fn from_text(text: &str) -> Result<HashMap<Position, Tile>, MapError> {
+    let mut map = HashMap::new();
+    let mut origin = None;
+    let mut oxygen = None;
+
+    for (y, line) in (0..).zip(text.lines()) {
+        for (x, c) in (0..).zip(line.chars()) {
+            let at = |n: Coordinate| usize::try_from(n).unwrap() + 1;
+            let tile = match c {
+                ' ' => continue,
+                '*' => Tile::Origin,
+                '.' => Tile::Empty,
+                '#' => Tile::Wall,
+                'O' => Tile::Oxygen,
+                c => return Err(MapError::BadTile(at(y), at(x), c)),
+            };
+            if tile == Tile::Origin && origin.replace(Position(x, y)).is_some() {
+                return Err(MapError::ExtraOrigin(at(y), at(x)));
+            }
+            if tile == Tile::Oxygen && oxygen.replace(Position(x, y)).is_some() {
+                return Err(MapError::ExtraOxygen(at(y), at(x)));
+            }
+            map.insert(Position(x, y), tile);
+        }
+    }
+
+    if oxygen.is_none() {
+        return Err(MapError::NoOxygen);
+    }
+    let origin = origin.unwrap_or(Position(0, 0));
+
+    Ok(map
+        .into_iter()
+        .map(|(Position(x, y), tile)| (Position(x - origin.0, y - origin.1), tile))
+        .collect())
+}
+
+pub fn save(path: impl AsRef<Path>, map: &HashMap<Position, Tile>) -> io::Result<()> {
+    fs::write(path, to_text(map))
+}
+
+pub fn load(path: impl AsRef<Path>) -> Result<HashMap<Position, Tile>, MapError> {
+    from_text(&fs::read_to_string(path)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let text = " ##\n#..##\n#*#..#\n#.O.#\n ###\n";
+        let map = from_text(text).unwrap();
+        assert_eq!(Some(&Tile::Origin), map.get(&Position(0, 0)));
+        assert_eq!(Some(&Tile::Oxygen), map.get(&Position(1, 1)));
+        assert_eq!(Some(&Tile::Wall), map.get(&Position(0, -2)));
+        assert_eq!(None, map.get(&Position(-1, -2)));
+        assert_eq!(text, to_text(&map));
+
+        let file = format!("aoc-2019-15-maze-{}.txt", std::process::id());
+        let path = std::env::temp_dir().join(file);
+        save(&path, &map).unwrap();
+        assert_eq!(map, load(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            from_text("##\n#D#"),
+            Err(MapError::BadTile(2, 2, 'D'))
+        ));
+        assert!(matches!(
+            from_text("#*.\n#O*"),
+            Err(MapError::ExtraOrigin(2, 3))
+        ));
+        assert!(matches!(
+            from_text("#O.\n.#O"),
+            Err(MapError::ExtraOxygen(2, 3))
+        ));
+        assert!(matches!(from_text("#*.#\n"), Err(MapError::NoOxygen)));
+    }
+}
@@ -0,0 +1,249 @@
+//! Packs the robot's moves into a main routine that calls a handful of
+//! movement functions, each of which fits in the robot's memory.
+//!
+//! ```text
+//! A,B,C,B,A,C
+//! R,8,R,8
+//! R,4,R,4,R,8
+//! L,6,L,2
+//! ```
+//!
+//! The search is exhaustive: at each point in the move list the main
+//! routine either calls a function that matches what comes next, or
+//! defines a new one from some prefix of it. Functions are named in the
+//! order the main routine first calls them, so no encoding turns up
+//! twice under different names.
+
+use crate::Move;
+
+/// Functions are named with capital letters.
+const MAX_FUNCTIONS: usize = 26;
+
+fn name(f: usize) -> char {
+    char::from(b'A' + u8::try_from(f).unwrap())
+}
+
+/// How much the robot can hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// How many movement functions there are.
+    pub functions: usize,
+    /// The most characters any one routine can be, not counting the
+    /// newline.
+    pub max_len: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            functions: 3,
+            max_len: 20,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Encoding {
+    /// Which function the main routine calls at each step.
+    pub main: Vec<usize>,
+    pub functions: Vec<Vec<Move>>,
+}
+
+impl Encoding {
+    /// The moves the robot ends up making.
+    pub fn expand(&self) -> Vec<Move> {
+        self.main
+            .iter()
+            .flat_map(|&f| self.functions[f].iter().cloned())
+            .collect()
+    }
+
+    pub fn main_ascii(&self) -> String {
+        let calls: Vec<_> = self.main.iter().map(|&f| name(f).to_string()).collect();
+        calls.join(",")
+    }
+
+    /// The routines as the robot reads them, a line each. There's
+    /// always a line for each of `functions`, so functions the main
+    /// routine never calls are filled in with a copy of the first.
+    pub fn to_ascii(&self, functions: usize) -> String {
+        let mut sb = self.main_ascii() + "\n";
+        for f in 0..functions {
+            let moves = self.functions.get(f).unwrap_or(&self.functions[0]);
+            sb += &function_ascii(moves);
+            sb += "\n";
+        }
+        sb
+    }
+}
+
+fn function_ascii(moves: &[Move]) -> String {
+    moves
+        .iter()
+        .map(|m| m.to_ascii())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The first encoding of `moves` that fits in `limits`, if there is one.
+pub fn compress(moves: &[Move], limits: &Limits) -> Option<Encoding> {
+    let mut first = None;
+    search(moves, limits, &mut |encoding| {
+        first = Some(encoding);
+        false
+    });
+    first
+}
+
+/// Every encoding of `moves` that fits in `limits`.
+pub fn encodings(moves: &[Move], limits: &Limits) -> Vec<Encoding> {
+    let mut all = vec![];
+    search(moves, limits, &mut |encoding| {
+        all.push(encoding);
+        true
+    });
+    all
+}
+
+/// Calls `found` with each encoding until it returns false.
+fn search(moves: &[Move], limits: &Limits, found: &mut dyn FnMut(Encoding) -> bool) {
+    if moves.is_empty() || limits.functions == 0 || limits.functions > MAX_FUNCTIONS {
+        return;
+    }
+    let mut encoding = Encoding {
+        main: vec![],
+        functions: vec![],
+    };
+    extend(moves, 0, limits, &mut encoding, found);
+}
+
+/// Tries every way of encoding `moves[at..]` given the calls and
+/// functions already in `encoding`. Returns false once `found` has had
+/// enough.
+fn extend(
+    moves: &[Move],
+    at: usize,
+    limits: &Limits,
+    encoding: &mut Encoding,
+    found: &mut dyn FnMut(Encoding) -> bool,
+) -> bool {
+    if at == moves.len() {
+        return found(encoding.clone());
+    }
+    // one letter per call, with commas between
+    if (encoding.main.len() + 1) * 2 - 1 > limits.max_len {
+        return true;
+    }
+
+    for f in 0..encoding.functions.len() {
+        if moves[at..].starts_with(&encoding.functions[f]) {
+            let next = at + encoding.functions[f].len();
+            encoding.main.push(f);
+            let more = extend(moves, next, limits, encoding, found);
+            encoding.main.pop();
+            if !more {
+                return false;
+            }
+        }
+    }
+
+    if encoding.functions.len() < limits.functions {
+        let f = encoding.functions.len();
+        let mut len = 0;
+        for end in at + 1..=moves.len() {
+            len += moves[end - 1].to_ascii().len() + usize::from(end > at + 1);
+            if len > limits.max_len {
+                break;
+            }
+            encoding.functions.push(moves[at..end].to_vec());
+            encoding.main.push(f);
+            let more = extend(moves, end, limits, encoding, found);
+            encoding.main.pop();
+            encoding.functions.pop();
+            if !more {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::Turn::{Left, Right};
+
+    fn example() -> Vec<Move> {
+        [
+            (Right, 8),
+            (Right, 8),
+            (Right, 4),
+            (Right, 4),
+            (Right, 8),
+            (Left, 6),
+            (Left, 2),
+            (Right, 4),
+            (Right, 4),
+            (Right, 8),
+            (Right, 8),
+            (Right, 8),
+            (Left, 6),
+            (Left, 2),
+        ]
+        .into_iter()
+        .map(|(t, n)| Move(t, n))
+        .collect()
+    }
+
+    #[test]
+    fn compress_example() {
+        let moves = example();
+        let limits = Limits::default();
+        let encoding = compress(&moves, &limits).unwrap();
+        assert_eq!(moves, encoding.expand());
+
+        let ascii = encoding.to_ascii(limits.functions);
+        assert_eq!(4, ascii.lines().count());
+        assert!(ascii.lines().all(|line| line.len() <= limits.max_len));
+    }
+
+    #[test]
+    fn all_encodings() {
+        let moves = example();
+        let limits = Limits::default();
+        let all = encodings(&moves, &limits);
+
+        // the one from the puzzle text
+        let given = Encoding {
+            main: vec![0, 1, 2, 1, 0, 2],
+            functions: vec![
+                moves[0..2].to_vec(),
+                moves[2..5].to_vec(),
+                moves[5..7].to_vec(),
+            ],
+        };
+        assert!(all.contains(&given));
+        assert_eq!(
+            "A,B,C,B,A,C\nR,8,R,8\nR,4,R,4,R,8\nL,6,L,2\n",
+            given.to_ascii(3)
+        );
+
+        for encoding in &all {
+            assert_eq!(moves, encoding.expand());
+            assert!(encoding.main_ascii().len() <= limits.max_len);
+        }
+        for (i, a) in all.iter().enumerate() {
+            assert!(all[i + 1..].iter().all(|b| a != b));
+        }
+
+        // too tight for anything
+        let tight = Limits {
+            functions: 2,
+            max_len: 8,
+        };
+        assert!(encodings(&moves, &tight).is_empty());
+        assert_eq!(None, compress(&moves, &tight));
+    }
+}
@@ -1,15 +1,23 @@
 #![cfg_attr(not(test), allow(dead_code))]
 
+mod compress;
 mod computer;
+mod droid;
+mod plan;
+mod simulator;
+mod video;
+use compress::Limits;
 use computer::Computer;
 use computer::Intcode;
+use droid::{Codec, Droid, IntcodeDroid};
 
 type AdventResult = usize;
 
 use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::fs;
-
-use regex::Regex;
+use std::io::{self, Write};
+use std::path::Path;
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 struct Position(i32, i32);
@@ -212,75 +220,15 @@ impl Scaffolding {
             .join(",")
     }
 
-    // Our full program looks something like
-    //   L1,R1,L10,L8,R4,...
-    //
-    // We want to find three subsequences of moves that are repeated
-    // and replace their occurences with A,B,C.
-    //
-    // This is pretty hard in general, but we do have a constraint and
-    // then can make some simplifying assumptions.
-    //
-    // The constraint we're given is that no part of the program can
-    // be longer than 20 characters.
-    //
-    // Our compressed output "A,B,A,C,..." can only have at most 10
-    // ABC symbols in it (need a comma after each). So each ABC needs
-    // to encode a little more than 15.2 characters from the full
-    // program. Our sequences need to be long, then.
-    //
-    // Let's try starting at the beginning of the string and finding
-    // the longest sequence that has a repeat later in the string,
-    // call that A, and then repeat.
-    //
-    // Not the most general solution, but it's scoped enough to be
-    // doable as part2 of this AOC.
-    //
-    fn program_moves_compressed(&self) -> String {
-        // the format! macro only takes a literal string so can't make
-        // the RE template a const
-        const SUBSEQ_MIN_LEN: usize = 3;
-        const SUBSEQ_MAX_LEN: usize = 20;
-        const RE_ANCHOR_COUNT: usize = 2;
-        const REPLACEMENTS: [&str; 3] = ["A", "B", "C"];
-
-        let full_prog = self.program_moves_full();
-        let mut compressed_prog = full_prog.clone();
-        let mut expansions = vec![];
-
-        'abc: for abc in REPLACEMENTS {
-            let mut maxlen = SUBSEQ_MAX_LEN - RE_ANCHOR_COUNT;
-            while maxlen >= SUBSEQ_MIN_LEN {
-                let re = Regex::new(&format!(
-                    "[LR][LR0-9,]{{{},{}}}[0-9],",
-                    SUBSEQ_MIN_LEN, maxlen
-                ))
-                .unwrap();
-                let m = re
-                    .find(&compressed_prog)
-                    .expect("RE should always match something");
-                let subseq_comma = m.as_str();
-                let subseq = &subseq_comma[..subseq_comma.len() - 1];
-                let trial = compressed_prog.replace(subseq, abc);
-                if compressed_prog.len() - trial.len() > subseq.len() {
-                    expansions.push(String::from(subseq));
-                    compressed_prog = trial;
-                    continue 'abc;
-                }
-
-                maxlen = subseq.len() - RE_ANCHOR_COUNT - 1;
-            }
-
-            panic!("failed to find a subseq for {}", abc);
-        }
-
-        compressed_prog += "\n";
-        for ex in &expansions {
-            compressed_prog += ex;
-            compressed_prog += "\n";
-        }
+    /// The robot's routines, with the moves split into functions
+    /// that fit in its memory, and whether to turn on its video feed.
+    fn program_moves_compressed(&self, video: bool) -> String {
+        let limits = Limits::default();
+        let (_, encoding) =
+            plan::plan(self, &limits).expect("some route should fit in the movement functions");
 
-        compressed_prog += "n\n"; // no live camera
+        let mut compressed_prog = encoding.to_ascii(limits.functions);
+        compressed_prog += if video { "y\n" } else { "n\n" };
 
         compressed_prog
     }
@@ -290,15 +238,6 @@ fn input() -> String {
     fs::read_to_string("input.txt").expect("Can't find input.txt")
 }
 
-fn intcodes_to_ascii<I>(intcodes: I) -> String
-where
-    I: Iterator<Item = Intcode>,
-{
-    intcodes
-        .map(|n| char::from(u8::try_from(n).unwrap()))
-        .collect()
-}
-
 fn ascii_to_intcodes(ascii: &str) -> Vec<Intcode> {
     ascii
         .chars()
@@ -306,37 +245,106 @@ fn ascii_to_intcodes(ascii: &str) -> Vec<Intcode> {
         .collect()
 }
 
+/// What the vacuum robot prints: ASCII, apart from the dust it
+/// collected at the very end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Output {
+    Char(char),
+    Dust(AdventResult),
+}
+
+/// The text in `outputs`.
+fn text(outputs: &[Output]) -> String {
+    outputs
+        .iter()
+        .filter_map(|o| match o {
+            Output::Char(c) => Some(*c),
+            Output::Dust(_) => None,
+        })
+        .collect()
+}
+
+/// The vacuum robot is given its routines a line at a time, and prints
+/// back what its camera sees and what it wants next.
+struct AsciiCodec;
+
+impl Codec for AsciiCodec {
+    type Command = String;
+    type Reading = Output;
+
+    fn encode(&self, line: &String) -> Vec<Intcode> {
+        ascii_to_intcodes(&format!("{}\n", line))
+    }
+
+    fn decode(&self, outputs: &mut VecDeque<Intcode>) -> Option<Output> {
+        outputs.pop_front().map(|n| match u8::try_from(n) {
+            Ok(c) if c.is_ascii() => Output::Char(char::from(c)),
+            _ => Output::Dust(n as AdventResult),
+        })
+    }
+}
+
+/// The scaffolding as the robot's camera sees it before it sets off.
+fn camera_view(input: &str) -> Scaffolding {
+    let mut droid = IntcodeDroid::new(Computer::parse_program(input), AsciiCodec);
+    let outputs = droid.start();
+    assert!(droid.is_halted());
+    Scaffolding::new(&text(&outputs))
+}
+
 fn do_part1(input: &str) -> AdventResult {
-    let mut computer = Computer::new(Computer::parse_program(input));
-    computer.start();
-    assert!(computer.is_halted());
-    let ascii = intcodes_to_ascii(computer.consume_output_buffer());
-    let s = Scaffolding::new(&ascii);
+    let s = camera_view(input);
     s.calibration_parameter()
 }
 
-fn do_part2(input: &str) -> AdventResult {
-    let mut computer = Computer::new(Computer::parse_program(input));
-    computer.start();
-    assert!(computer.is_halted());
-    let s = Scaffolding::new(&intcodes_to_ascii(computer.consume_output_buffer()));
+/// Sends the robot round the scaffold, returning everything it
+/// printed on the way.
+fn run_robot(input: &str, video: bool) -> Vec<Output> {
+    let s = camera_view(input);
 
-    let ascii_input = s.program_moves_compressed();
+    let ascii_input = s.program_moves_compressed(video);
+    if let Err(e) = simulator::check(&s, &ascii_input) {
+        panic!("bad routines: {}", e);
+    }
 
-    computer = Computer::new(Computer::parse_program(input));
+    let mut droid = IntcodeDroid::new(Computer::parse_program(input), AsciiCodec);
+    droid.computer_mut().write(0, 2);
 
-    let inputs = ascii_to_intcodes(&ascii_input);
-    for input in inputs {
-        computer.buffer_input(input);
+    let mut outputs = droid.start();
+    for line in ascii_input.lines() {
+        outputs.extend(droid.send(line.to_owned()));
     }
+    assert!(droid.is_halted());
+
+    outputs
+}
 
-    computer.write(0, 2);
+fn do_part2(input: &str) -> AdventResult {
+    match run_robot(input, false).last() {
+        Some(&Output::Dust(dust)) => dust,
+        x => panic!("expected the dust count, got {:?}", x),
+    }
+}
 
-    computer.start();
-    assert!(computer.is_halted());
+/// Part 2 with the video feed on, played to `out` at `fps` and, given
+/// a path, saved as an asciicast too.
+fn watch_part2(
+    input: &str,
+    fps: u32,
+    out: &mut impl Write,
+    cast: Option<&Path>,
+) -> io::Result<AdventResult> {
+    let feed = video::split(&run_robot(input, true));
+    video::play(&feed.frames, fps, out)?;
+    if let Some(path) = cast {
+        fs::write(path, video::asciicast(&feed.frames, fps))?;
+    }
+    Ok(feed.dust.expect("robot should report the dust"))
+}
 
-    let outputs: Vec<_> = computer.consume_output_buffer().collect();
-    *(outputs.last().unwrap()) as usize
+/// Plays part 2's video feed on the terminal; see `watch_part2`.
+pub fn watch(fps: u32, cast: Option<&Path>) -> io::Result<AdventResult> {
+    watch_part2(&input(), fps, &mut io::stdout(), cast)
 }
 
 fn part1() -> AdventResult {
@@ -412,7 +420,7 @@ mod test {
         // make sure that it follows the rules and expands to the
         // epxected full program.
 
-        let compressed = s.program_moves_compressed();
+        let compressed = s.program_moves_compressed(false);
         for line in compressed.lines() {
             assert!(line.len() <= 20);
         }
@@ -440,4 +448,16 @@ mod test {
     fn part2_solution() {
         assert_eq!(1289413, part2());
     }
+
+    #[test]
+    fn part2_video() {
+        let file = format!("aoc-2019-17-{}.cast", std::process::id());
+        let path = std::env::temp_dir().join(file);
+        let dust = watch_part2(&input(), 10_000, &mut io::sink(), Some(&path)).unwrap();
+        assert_eq!(1289413, dust);
+        let cast = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        // a header, then a line per frame
+        assert_eq!(1 + 345, cast.lines().count());
+    }
 }
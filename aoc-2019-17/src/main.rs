@@ -0,0 +1,15 @@
+use std::env;
+use std::path::PathBuf;
+
+// `cargo run -- [fps] [recording.cast]` to watch the robot clean the
+// scaffold, and save it for `asciinema play` if given somewhere to
+fn main() {
+    let mut args = env::args().skip(1);
+    let fps = args
+        .next()
+        .map_or(30, |fps| fps.parse().expect("fps should be a number"));
+    let cast = args.next().map(PathBuf::from);
+
+    let dust = aoc_2019_17::watch(fps, cast.as_deref()).expect("can't show the video feed");
+    println!("Dust collected: {}", dust);
+}
@@ -0,0 +1,191 @@
+//! Finds routes over the scaffold that the robot's routines can hold.
+//!
+//! Driving straight through every intersection is only one way round.
+//! The robot may also turn at an intersection, so long as it never
+//! drives along the same stretch of scaffold twice, and every such
+//! route is a candidate for the compressor.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::compress::{self, Encoding, Limits};
+use crate::{Direction, Move, Position, Scaffolding, Turn};
+
+/// A step between two neighbouring tiles, either way round.
+type Edge = (Position, Position);
+
+fn edge(a: Position, b: Position) -> Edge {
+    if (a.0, a.1) < (b.0, b.1) {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+struct Walk<'a> {
+    scaffolding: &'a Scaffolding,
+    visits: HashMap<Position, u32>,
+    used: HashSet<Edge>,
+    moves: Vec<Move>,
+}
+
+/// Calls `found` with the moves of each route from the robot's start
+/// that visits every tile, until it returns false. Routes come
+/// straightest first, so the first one is the robot's plain
+/// `robot_moves`.
+pub fn traversals(scaffolding: &Scaffolding, found: &mut dyn FnMut(&[Move]) -> bool) {
+    let mut walk = Walk {
+        scaffolding,
+        visits: HashMap::from([(scaffolding.origin, 1)]),
+        used: HashSet::new(),
+        moves: vec![],
+    };
+    walk.extend(scaffolding.origin, scaffolding.orientation, found);
+}
+
+/// The first route whose moves fit in `limits`, along with how.
+pub fn plan(scaffolding: &Scaffolding, limits: &Limits) -> Option<(Vec<Move>, Encoding)> {
+    let mut planned = None;
+    traversals(scaffolding, &mut |moves| {
+        planned = compress::compress(moves, limits).map(|e| (moves.to_vec(), e));
+        planned.is_none()
+    });
+    planned
+}
+
+impl Walk<'_> {
+    /// Carries on from `pos`, facing `heading`. Returns false once
+    /// `found` has had enough.
+    fn extend(
+        &mut self,
+        pos: Position,
+        heading: Direction,
+        found: &mut dyn FnMut(&[Move]) -> bool,
+    ) -> bool {
+        if self.visits.len() == self.scaffolding.locations.len() {
+            return found(&self.moves);
+        }
+
+        // the robot can't go straight on before its first turn
+        let straight = (!self.moves.is_empty()).then_some(None);
+        let options = straight.into_iter().chain(Turn::ALL.into_iter().map(Some));
+
+        for turn in options {
+            let heading = turn.map_or(heading, |t| heading.turn(t));
+            let next = heading.of(&pos);
+            if !self.scaffolding.locations.contains(&next) || !self.used.insert(edge(pos, next)) {
+                continue;
+            }
+
+            match turn {
+                Some(t) => self.moves.push(Move(t, 1)),
+                None => self.moves.last_mut().unwrap().1 += 1,
+            }
+            *self.visits.entry(next).or_default() += 1;
+
+            let more = self.extend(next, heading, found);
+
+            if let Some(n) = self.visits.get_mut(&next) {
+                *n -= 1;
+                if *n == 0 {
+                    self.visits.remove(&next);
+                }
+            }
+            match turn {
+                Some(_) => {
+                    self.moves.pop();
+                }
+                None => self.moves.last_mut().unwrap().1 -= 1,
+            }
+            self.used.remove(&edge(pos, next));
+
+            if !more {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The tiles the robot drives over making `moves`.
+    fn visited(s: &Scaffolding, moves: &[Move]) -> HashSet<Position> {
+        let mut pos = s.origin;
+        let mut heading = s.orientation;
+        let mut tiles = HashSet::from([pos]);
+        for m in moves {
+            heading = heading.turn(m.0);
+            for _ in 0..m.1 {
+                pos = heading.of(&pos);
+                tiles.insert(pos);
+            }
+        }
+        tiles
+    }
+
+    #[test]
+    fn example_traversals() {
+        let ascii = "\
+#######...#####
+#.....#...#...#
+#.....#...#...#
+......#...#...#
+......#...###.#
+......#.....#.#
+^########...#.#
+......#.#...#.#
+......#########
+........#...#..
+....#########..
+....#...#......
+....#...#......
+....#...#......
+....#####......";
+        let s = Scaffolding::new(ascii);
+
+        let mut all = vec![];
+        traversals(&s, &mut |moves| {
+            all.push(moves.to_vec());
+            true
+        });
+
+        assert_eq!(16, all.len());
+        assert_eq!(s.robot_moves(), all[0]);
+        for (i, moves) in all.iter().enumerate() {
+            assert_eq!(s.locations, visited(&s, moves));
+            assert!(all[i + 1..].iter().all(|other| other != moves));
+        }
+    }
+
+    #[test]
+    fn turn_at_intersection() {
+        let ascii = "\
+######.....
+#....#.....
+#....#.....
+#....#.....
+#....#.....
+##########^
+.....#.....
+.....#.....
+.....#.....
+.....#.....
+.....######";
+        let s = Scaffolding::new(ascii);
+        let limits = Limits {
+            functions: 2,
+            max_len: 10,
+        };
+
+        // straight through, L,10,R,5,R,5,R,10,L,5 needs three functions
+        assert_eq!(None, compress::compress(&s.robot_moves(), &limits));
+
+        let (moves, encoding) = plan(&s, &limits).unwrap();
+        assert_ne!(s.robot_moves(), moves);
+        assert_eq!(s.locations, visited(&s, &moves));
+        assert_eq!(moves, encoding.expand());
+    }
+}
@@ -0,0 +1,190 @@
+//! Drives the robot's routines over the scaffold without the Intcode
+//! program, so a bad routine shows up as something more useful than
+//! the robot's own complaint.
+//!
+//! Routines are read as the robot reads them: the main routine on the
+//! first line, then a line for each movement function, and optionally
+//! the `y` or `n` answer for the video feed.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::compress::Encoding;
+use crate::{Move, Position, Scaffolding, Turn};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimError {
+    /// There's no main routine.
+    NoMain,
+    /// The main routine calls something that isn't a function.
+    BadCall(String),
+    /// A function has something that isn't a turn and a distance.
+    BadMove(String),
+    /// The robot drives off the edge partway through a move, counting
+    /// moves from 1 across the whole expanded routine.
+    LeavesScaffold { step: usize, at: Position },
+    /// Scaffold tiles the robot never gets to, in reading order.
+    Unvisited(Vec<Position>),
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimError::NoMain => write!(f, "no main routine"),
+            SimError::BadCall(call) => write!(f, "main routine calls unknown function {:?}", call),
+            SimError::BadMove(m) => write!(f, "bad move {:?}", m),
+            SimError::LeavesScaffold { step, at } => {
+                write!(f, "step {} leaves scaffold at ({}, {})", step, at.0, at.1)
+            }
+            SimError::Unvisited(tiles) => {
+                let Position(x, y) = tiles[0];
+                write!(
+                    f,
+                    "{} scaffold tiles never visited, first at ({}, {})",
+                    tiles.len(),
+                    x,
+                    y
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SimError {}
+
+/// Reads the main routine and movement functions.
+pub fn parse(routines: &str) -> Result<Encoding, SimError> {
+    let mut lines = routines
+        .lines()
+        .filter(|&line| !matches!(line, "y" | "n" | ""));
+    let main = lines.next().ok_or(SimError::NoMain)?;
+    let functions = lines.map(parse_function).collect::<Result<Vec<_>, _>>()?;
+
+    let main = main
+        .split(',')
+        .map(|call| match call.as_bytes() {
+            &[c @ b'A'..=b'Z'] if usize::from(c - b'A') < functions.len() => {
+                Ok(usize::from(c - b'A'))
+            }
+            _ => Err(SimError::BadCall(call.to_owned())),
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(Encoding { main, functions })
+}
+
+fn parse_function(line: &str) -> Result<Vec<Move>, SimError> {
+    let parts: Vec<_> = line.split(',').collect();
+    parts
+        .chunks(2)
+        .map(|pair| {
+            let bad = || SimError::BadMove(pair.join(","));
+            let turn = match pair[0] {
+                "L" => Turn::Left,
+                "R" => Turn::Right,
+                _ => return Err(bad()),
+            };
+            let distance = pair.get(1).and_then(|n| n.parse().ok()).ok_or_else(bad)?;
+            Ok(Move(turn, distance))
+        })
+        .collect()
+}
+
+/// Drives the robot through `encoding`, checking it stays on the
+/// scaffold and visits all of it.
+pub fn simulate(scaffolding: &Scaffolding, encoding: &Encoding) -> Result<(), SimError> {
+    let mut pos = scaffolding.origin;
+    let mut heading = scaffolding.orientation;
+    let mut visited = HashSet::from([pos]);
+
+    for (step, Move(turn, distance)) in (1..).zip(encoding.expand()) {
+        heading = heading.turn(turn);
+        for _ in 0..distance {
+            pos = heading.of(&pos);
+            if !scaffolding.locations.contains(&pos) {
+                return Err(SimError::LeavesScaffold { step, at: pos });
+            }
+            visited.insert(pos);
+        }
+    }
+
+    let mut unvisited: Vec<_> = scaffolding
+        .locations
+        .difference(&visited)
+        .copied()
+        .collect();
+    if unvisited.is_empty() {
+        Ok(())
+    } else {
+        unvisited.sort_by_key(|p| (p.1, p.0));
+        Err(SimError::Unvisited(unvisited))
+    }
+}
+
+/// Parses `routines` and drives the robot through them.
+pub fn check(scaffolding: &Scaffolding, routines: &str) -> Result<(), SimError> {
+    simulate(scaffolding, &parse(routines)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+#######...#####
+#.....#...#...#
+#.....#...#...#
+......#...#...#
+......#...###.#
+......#.....#.#
+^########...#.#
+......#.#...#.#
+......#########
+........#...#..
+....#########..
+....#...#......
+....#...#......
+....#...#......
+....#####......";
+
+    #[test]
+    fn example_routines() {
+        let s = Scaffolding::new(EXAMPLE);
+        let routines = "A,B,C,B,A,C\nR,8,R,8\nR,4,R,4,R,8\nL,6,L,2\nn\n";
+        assert_eq!(Ok(()), check(&s, routines));
+        assert_eq!(Ok(()), check(&s, &s.program_moves_compressed(false)));
+    }
+
+    #[test]
+    fn diagnostics() {
+        let s = Scaffolding::new(EXAMPLE);
+
+        // stopping one short, the next move turns into a gap
+        let err = check(&s, "A,B,C,B,A,C\nR,8,R,8\nR,4,R,3,R,8\nL,6,L,2\n").unwrap_err();
+        assert_eq!(
+            SimError::LeavesScaffold {
+                step: 5,
+                at: Position(5, 11)
+            },
+            err
+        );
+        assert_eq!("step 5 leaves scaffold at (5, 11)", err.to_string());
+
+        let err = check(&s, "A,B\nR,8,R,8\nR,4,R,4,R,8\n").unwrap_err();
+        assert!(matches!(err, SimError::Unvisited(_)));
+        assert_eq!(
+            "45 scaffold tiles never visited, first at (0, 0)",
+            err.to_string()
+        );
+
+        assert_eq!(
+            Err(SimError::BadCall("D".to_owned())),
+            check(&s, "A,D\nR,8\n")
+        );
+        assert_eq!(
+            Err(SimError::BadMove("R".to_owned())),
+            check(&s, "A\nR,8,R\n")
+        );
+        assert_eq!(Err(SimError::NoMain), check(&s, "\n"));
+    }
+}
@@ -0,0 +1,141 @@
+//! The robot's continuous video feed.
+//!
+//! With the feed on, the robot prints the camera view after every move
+//! it makes, with a blank line after each. The prompts for its routines
+//! come through the same way, and the dust count is the one output
+//! that isn't ASCII at all.
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+use crate::{text, AdventResult, Output};
+
+/// What the camera draws: scaffold, open space, and the robot facing
+/// each way or tumbling through space.
+const GLYPHS: &str = ".#^v<>X";
+
+/// Moves the cursor home and clears the screen.
+const CLEAR: &str = "\x1b[H\x1b[2J";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Feed {
+    /// Camera views, in order.
+    pub frames: Vec<String>,
+    /// How much dust the robot collected, once it's done.
+    pub dust: Option<AdventResult>,
+}
+
+/// Splits the robot's output into camera frames and the dust count,
+/// dropping anything else it said along the way.
+pub fn split(output: &[Output]) -> Feed {
+    let (ascii, dust) = match output.split_last() {
+        Some((&Output::Dust(n), rest)) => (rest, Some(n)),
+        _ => (output, None),
+    };
+
+    let frames = text(ascii)
+        .split("\n\n")
+        .filter(|&chunk| {
+            !chunk.is_empty() && chunk.chars().all(|c| c == '\n' || GLYPHS.contains(c))
+        })
+        .map(|chunk| chunk.trim_end().to_owned())
+        .collect();
+
+    Feed { frames, dust }
+}
+
+fn frame_duration(fps: u32) -> Duration {
+    Duration::from_secs(1) / fps.max(1)
+}
+
+/// Draws each frame over the last, `fps` times a second.
+pub fn play(frames: &[String], fps: u32, out: &mut impl Write) -> io::Result<()> {
+    for frame in frames {
+        writeln!(out, "{}{}", CLEAR, frame)?;
+        out.flush()?;
+        thread::sleep(frame_duration(fps));
+    }
+    Ok(())
+}
+
+/// The frames as an asciicast v2 recording, for `asciinema play`.
+pub fn asciicast(frames: &[String], fps: u32) -> String {
+    let width = frames
+        .iter()
+        .flat_map(|f| f.lines())
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let height = frames.iter().map(|f| f.lines().count()).max().unwrap_or(0);
+
+    let mut sb = format!(
+        "{{\"version\": 2, \"width\": {}, \"height\": {}}}\n",
+        width, height
+    );
+    for (i, frame) in frames.iter().enumerate() {
+        let time = frame_duration(fps).as_secs_f64() * i as f64;
+        let data = format!("{}{}\r\n", CLEAR, frame.replace('\n', "\r\n"));
+        writeln!(sb, "[{:.6}, \"o\", {}]", time, json_string(&data)).unwrap();
+    }
+    sb
+}
+
+fn json_string(s: &str) -> String {
+    let mut sb = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => sb += "\\\"",
+            '\\' => sb += "\\\\",
+            '\n' => sb += "\\n",
+            '\r' => sb += "\\r",
+            c if c.is_control() => write!(sb, "\\u{:04x}", c as u32).unwrap(),
+            c => sb.push(c),
+        }
+    }
+    sb.push('"');
+    sb
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn chars(s: &str) -> Vec<Output> {
+        s.chars().map(Output::Char).collect()
+    }
+
+    #[test]
+    fn split_frames() {
+        let mut output = chars("#^#\n...\n\nMain:\nContinuous video feed?\n\n");
+        output.extend(chars("#>#\n...\n\n#X#\n...\n\n"));
+        output.push(Output::Dust(1234));
+
+        let feed = split(&output);
+        assert_eq!(vec!["#^#\n...", "#>#\n...", "#X#\n..."], feed.frames);
+        assert_eq!(Some(1234), feed.dust);
+
+        assert_eq!(None, split(&chars("#^#\n\n")).dust);
+    }
+
+    #[test]
+    fn play_frames() {
+        let frames = vec!["#^".to_owned(), "#>".to_owned()];
+        let mut out = vec![];
+        play(&frames, 1000, &mut out).unwrap();
+        assert_eq!(
+            "\x1b[H\x1b[2J#^\n\x1b[H\x1b[2J#>\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn asciicast_frames() {
+        let frames = vec!["#^\n.#".to_owned(), "#>\n.#".to_owned()];
+        let expected = "{\"version\": 2, \"width\": 2, \"height\": 2}\n\
+                        [0.000000, \"o\", \"\\u001b[H\\u001b[2J#^\\r\\n.#\\r\\n\"]\n\
+                        [0.250000, \"o\", \"\\u001b[H\\u001b[2J#>\\r\\n.#\\r\\n\"]\n";
+        assert_eq!(expected, asciicast(&frames, 4));
+    }
+}
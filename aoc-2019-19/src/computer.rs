@@ -1,7 +1,9 @@
 #![allow(dead_code)]
 
 use std::collections::VecDeque;
+use std::fmt;
 use std::mem::swap;
+use std::sync::Arc;
 
 pub type Intcode = i64;
 
@@ -84,7 +86,22 @@ enum ComputerState {
     AwaitingInput,
 }
 
+/// `reset` was called on a machine made with `Computer::new`, which
+/// doesn't keep its initial memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoImage;
+
+impl fmt::Display for NoImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "computer has no image to reset to")
+    }
+}
+
+impl std::error::Error for NoImage {}
+
 pub struct Computer {
+    /// What `reset` restores, for machines made with `from_image`.
+    image: Option<Arc<[Intcode]>>,
     memory: Vec<Intcode>,
     ip: Intcode,
     state: ComputerState,
@@ -101,6 +118,7 @@ impl Computer {
 
     pub fn new(memory: Vec<Intcode>) -> Self {
         Computer {
+            image: None,
             memory,
             inputs: VecDeque::new(),
             ip: 0,
@@ -111,6 +129,22 @@ impl Computer {
         }
     }
 
+    /// Creates a computer whose initial memory is a shared image, so
+    /// that many machines running one program only copy it once each,
+    /// and which can be `reset` back to it.
+    pub fn from_image(image: Arc<[Intcode]>) -> Self {
+        Computer {
+            memory: image.to_vec(),
+            image: Some(image),
+            inputs: VecDeque::new(),
+            ip: 0,
+            state: ComputerState::Initial,
+            outputs: VecDeque::new(),
+            op: None,
+            relative_base: 0,
+        }
+    }
+
     fn adjust_relative_base(&mut self, pa: Parameter) {
         let a = self.deref(&pa);
         self.relative_base += a;
@@ -280,6 +314,23 @@ impl Computer {
         });
     }
 
+    /// Restores the initial memory image and machine state in place.
+    /// The memory allocation is kept, so a machine can be reused for
+    /// many short runs without reallocating. Only machines made with
+    /// `from_image` have an image to go back to.
+    pub fn reset(&mut self) -> Result<(), NoImage> {
+        let image = self.image.as_ref().ok_or(NoImage)?;
+        self.memory.clear();
+        self.memory.extend_from_slice(image);
+        self.ip = 0;
+        self.state = ComputerState::Initial;
+        self.inputs.clear();
+        self.outputs.clear();
+        self.op = None;
+        self.relative_base = 0;
+        Ok(())
+    }
+
     pub fn result_addr0(&self) -> Intcode {
         assert_eq!(ComputerState::Halted, self.state);
         self.read(0)
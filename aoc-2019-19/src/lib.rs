@@ -2,11 +2,74 @@
 
 type AdventResult = usize;
 
+use std::collections::VecDeque;
 use std::fs;
 
 mod computer;
 use computer::{Computer, Intcode};
 
+mod droid;
+use droid::{Codec, Droid};
+
+mod symbolic;
+
+/// A point to send the drone to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Probe(usize, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Beam {
+    Stationary,
+    Pulled,
+}
+
+/// The drone is sent to a point and reports whether the beam pulls
+/// it there.
+struct DroneCodec;
+
+impl Codec for DroneCodec {
+    type Command = Probe;
+    type Reading = Beam;
+
+    fn encode(&self, &Probe(x, y): &Probe) -> Vec<Intcode> {
+        vec![x as Intcode, y as Intcode]
+    }
+
+    fn decode(&self, outputs: &mut VecDeque<Intcode>) -> Option<Beam> {
+        outputs.pop_front().map(|n| match n {
+            0 => Beam::Stationary,
+            1 => Beam::Pulled,
+            _ => panic!("unexpected drone status {}", n),
+        })
+    }
+}
+
+/// A fresh drone for every probe, since the program halts after each
+/// one.
+struct Drones<'a> {
+    program: &'a [Intcode],
+}
+
+impl Droid for Drones<'_> {
+    type Command = Probe;
+    type Reading = Beam;
+
+    fn send(&mut self, probe: Probe) -> Vec<Beam> {
+        let mut computer = Computer::new(self.program.to_vec());
+        for n in DroneCodec.encode(&probe) {
+            computer.buffer_input(n);
+        }
+        computer.start();
+
+        let mut outputs: VecDeque<_> = computer.consume_output_buffer().collect();
+        std::iter::from_fn(|| DroneCodec.decode(&mut outputs)).collect()
+    }
+
+    fn is_halted(&self) -> bool {
+        false
+    }
+}
+
 fn input() -> String {
     fs::read_to_string("input.txt").expect("Can't find input.txt")
 }
@@ -15,7 +78,7 @@ fn do_part1(input: &str) -> AdventResult {
     let mut count = 0;
 
     let prog = Computer::parse_program(input);
-    
+
     for y in 0..50 {
         for x in 0..50 {
             if check(&prog, x, y) {
@@ -27,42 +90,38 @@ fn do_part1(input: &str) -> AdventResult {
     count
 }
 
-fn check(prog: &Vec<Intcode>, x: usize, y: usize) -> bool {
-    let mut computer = Computer::new(prog.clone());
-    computer.buffer_input(x as Intcode);
-    computer.buffer_input(y as Intcode);
-    computer.start();
-    computer.consume_output().unwrap() != 0
+fn check(prog: &[Intcode], x: usize, y: usize) -> bool {
+    Drones { program: prog }.send(Probe(x, y)) == [Beam::Pulled]
 }
 
-fn check_rect_top_left(prog: &Vec<Intcode>, x: usize, y: usize, dim: usize) -> bool {
+fn check_rect_top_left(prog: &[Intcode], x: usize, y: usize, dim: usize) -> bool {
     let dim = dim - 1;
     // x,y top left
-    check(&prog, x, y) &&
-        check(&prog, x + dim, y) &&
-        check(&prog, x, y + dim) &&
-        check(&prog, x + dim, y + dim)
+    check(prog, x, y) &&
+        check(prog, x + dim, y) &&
+        check(prog, x, y + dim) &&
+        check(prog, x + dim, y + dim)
 }
 
-fn check_rect_top_right(prog: &Vec<Intcode>, x: usize, y: usize, dim: usize) -> bool {
+fn check_rect_top_right(prog: &[Intcode], x: usize, y: usize, dim: usize) -> bool {
     let dim = dim - 1;
     // x,y upper right
-    check(&prog, x, y) &&
-        check(&prog, x - dim, y) &&
-        check(&prog, x, y + dim) &&
-        check(&prog, x - dim, y + dim)
+    check(prog, x, y) &&
+        check(prog, x - dim, y) &&
+        check(prog, x, y + dim) &&
+        check(prog, x - dim, y + dim)
 }
 
 fn do_part2(input: &str) -> AdventResult {
-    let prog = Computer::parse_program(input);
+    let prog = &Computer::parse_program(input);
 
     // Find the right edge at y=100
     let mut y = 100;
     let mut x = 0;
-    while !check(&prog, x, y) {
+    while !check(prog, x, y) {
         x += 1;
     }
-    while check(&prog, x, y) {
+    while check(prog, x, y) {
         x += 1;
     }
     x -= 1;
@@ -70,12 +129,12 @@ fn do_part2(input: &str) -> AdventResult {
     // Hug the right edge while advancing each line, checking the four
     // corners based on the top-right being on the edge
     loop {
-        if check_rect_top_right(&prog, x, y, 100) {
+        if check_rect_top_right(prog, x, y, 100) {
             break;
         }
 
         y += 1;
-        while check(&prog, x, y) {
+        while check(prog, x, y) {
             x += 1;
         }
         x -= 1;
@@ -84,7 +143,7 @@ fn do_part2(input: &str) -> AdventResult {
 
     // Calculate the top-left
     x -= 99;
-    assert!(check_rect_top_left(&prog, x, y, 100));
+    assert!(check_rect_top_left(prog, x, y, 100));
 
     println!("Top Left corner at {} {} works", x, y);
     x * 10_000 + y
@@ -102,6 +161,8 @@ fn part2() -> AdventResult {
 mod test {
     use super::*;
 
+    use std::sync::Arc;
+
     #[test]
     fn part1_example() {
         // no example
@@ -112,6 +173,63 @@ mod test {
         // no example
     }
     
+    #[test]
+    fn symbolic_beam_query() {
+        use symbolic::{Constraint, Expr, SymbolicExecutor};
+
+        // Which point at least 20 rows down is pulled by the beam?
+        let mut executor = SymbolicExecutor::new(Computer::parse_program(&input()));
+        let x = executor.input_symbol("x", 0..=49);
+        let y = executor.input_symbol("y", 20..=49);
+
+        let solution = executor
+            .explore()
+            .iter()
+            .find_map(|path| {
+                let goal = Expr::equal(path.outputs[0].clone(), Expr::constant(1));
+                executor.solve_path(path, Constraint::new(goal, true))
+            })
+            .expect("beam reaches past row 20");
+
+        let prog = Computer::parse_program(&input());
+        assert!(check(&prog, solution[x] as usize, solution[y] as usize));
+    }
+
+    #[test]
+    fn reset_reuses_machine() {
+        let prog = Computer::parse_program(&input());
+        let expected: Vec<bool> = (0..10).map(|x| check(&prog, x, 10)).collect();
+
+        // a single machine, reset between runs, answers the same
+        let mut computer = Computer::from_image(Arc::from(prog.clone()));
+        for (x, &pulled) in expected.iter().enumerate() {
+            computer.buffer_input(x as Intcode);
+            computer.buffer_input(10);
+            computer.start();
+            assert_eq!(pulled, computer.consume_output().unwrap() != 0);
+            assert_eq!(Ok(()), computer.reset());
+        }
+
+        // one made from a plain Vec has nothing to go back to
+        let mut computer = Computer::new(prog);
+        assert_eq!(Err(computer::NoImage), computer.reset());
+    }
+
+    #[test]
+    fn drone_codec() {
+        use droid::IntcodeDroid;
+
+        // the beam always starts at the emitter
+        let mut drone = IntcodeDroid::new(Computer::parse_program(&input()), DroneCodec);
+        assert_eq!(vec![Beam::Pulled], drone.send(Probe(0, 0)));
+        assert!(drone.is_halted());
+
+        let prog = Computer::parse_program(&input());
+        let mut drones = Drones { program: &prog };
+        assert_eq!(vec![Beam::Pulled], drones.send(Probe(0, 0)));
+        assert_eq!(vec![Beam::Stationary], drones.send(Probe(49, 0)));
+    }
+
     #[test]
     fn part1_solution() {
         assert_eq!(147, part1());
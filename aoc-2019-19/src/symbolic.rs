@@ -0,0 +1,661 @@
+#![allow(dead_code)]
+
+//! Symbolic execution of Intcode programs.
+//!
+//! Chosen inputs and memory cells are replaced by symbols, and every
+//! value the program computes from them is kept as a formula. Where a
+//! conditional jump depends on a symbol, both sides are followed and
+//! each path records the condition it assumed. A small solver then
+//! finds symbol values that satisfy a path's constraints plus a goal,
+//! e.g. "output 0 equals 1".
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+
+pub type Intcode = i64;
+pub type Symbol = usize;
+pub type Value = Rc<Expr>;
+
+const OP_ADD: Intcode = 1;
+const OP_MUL: Intcode = 2;
+const OP_STORE_INPUT: Intcode = 3;
+const OP_WRITE_OUTPUT: Intcode = 4;
+const OP_JUMP_IF_TRUE: Intcode = 5;
+const OP_JUMP_IF_FALSE: Intcode = 6;
+const OP_LESS_THAN: Intcode = 7;
+const OP_EQUALS: Intcode = 8;
+const OP_ADJUST_RELATIVE_BASE: Intcode = 9;
+const OP_HALT: Intcode = 99;
+
+const PARAM_TYPE_POSITION: Intcode = 0;
+const PARAM_TYPE_IMMEDIATE: Intcode = 1;
+const PARAM_TYPE_RELATIVE: Intcode = 2;
+
+const DEFAULT_MAX_STEPS: usize = 1_000_000;
+const DEFAULT_MAX_PATHS: usize = 10_000;
+
+#[derive(Debug)]
+pub enum Expr {
+    Const(Intcode),
+    Sym(Symbol),
+    Add(Value, Value),
+    Mul(Value, Value),
+    Less(Value, Value),
+    Equal(Value, Value),
+    /// A read from a symbolic address, against the memory as it was at
+    /// the time of the read.
+    Select(Value, Rc<Vec<Value>>),
+}
+
+use Expr::*;
+
+impl Expr {
+    pub fn constant(n: Intcode) -> Value {
+        Rc::new(Const(n))
+    }
+
+    pub fn symbol(s: Symbol) -> Value {
+        Rc::new(Sym(s))
+    }
+
+    pub fn add(a: Value, b: Value) -> Value {
+        match (&*a, &*b) {
+            (Const(x), Const(y)) => Expr::constant(x.wrapping_add(*y)),
+            (Const(0), _) => b,
+            (_, Const(0)) => a,
+            _ => Rc::new(Add(a, b)),
+        }
+    }
+
+    pub fn mul(a: Value, b: Value) -> Value {
+        match (&*a, &*b) {
+            (Const(x), Const(y)) => Expr::constant(x.wrapping_mul(*y)),
+            (Const(0), _) | (_, Const(0)) => Expr::constant(0),
+            (Const(1), _) => b,
+            (_, Const(1)) => a,
+            _ => Rc::new(Mul(a, b)),
+        }
+    }
+
+    pub fn less(a: Value, b: Value) -> Value {
+        match (&*a, &*b) {
+            (Const(x), Const(y)) => Expr::constant(Intcode::from(x < y)),
+            _ if Rc::ptr_eq(&a, &b) => Expr::constant(0),
+            _ => Rc::new(Less(a, b)),
+        }
+    }
+
+    pub fn equal(a: Value, b: Value) -> Value {
+        match (&*a, &*b) {
+            (Const(x), Const(y)) => Expr::constant(Intcode::from(x == y)),
+            _ if Rc::ptr_eq(&a, &b) => Expr::constant(1),
+            _ => Rc::new(Equal(a, b)),
+        }
+    }
+
+    fn select(addr: Value, memory: Rc<Vec<Value>>) -> Value {
+        match &*addr {
+            Const(a) => read_cell(&memory, *a),
+            _ => Rc::new(Select(addr, memory)),
+        }
+    }
+
+    pub fn as_const(&self) -> Option<Intcode> {
+        match self {
+            Const(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn is_boolean(&self) -> bool {
+        matches!(self, Less(..) | Equal(..))
+    }
+
+    /// Evaluates with every symbol assigned.
+    pub fn eval(self: &Value, assignment: &[Intcode]) -> Intcode {
+        let assignment: Vec<_> = assignment.iter().map(|&n| Some(n)).collect();
+        self.substitute(&assignment, &mut HashMap::new())
+            .as_const()
+            .expect("every symbol is assigned")
+    }
+
+    /// Replaces assigned symbols by their values and simplifies.
+    /// Subexpressions are shared, so results are memoized by address.
+    fn substitute(
+        self: &Value,
+        assignment: &[Option<Intcode>],
+        memo: &mut HashMap<*const Expr, Value>,
+    ) -> Value {
+        if let Some(v) = memo.get(&Rc::as_ptr(self)) {
+            return v.clone();
+        }
+
+        let v = match &**self {
+            Const(_) => self.clone(),
+            Sym(s) => match assignment.get(*s).copied().flatten() {
+                Some(n) => Expr::constant(n),
+                None => self.clone(),
+            },
+            Add(a, b) => Expr::add(
+                a.substitute(assignment, memo),
+                b.substitute(assignment, memo),
+            ),
+            Mul(a, b) => Expr::mul(
+                a.substitute(assignment, memo),
+                b.substitute(assignment, memo),
+            ),
+            Less(a, b) => Expr::less(
+                a.substitute(assignment, memo),
+                b.substitute(assignment, memo),
+            ),
+            Equal(a, b) => Expr::equal(
+                a.substitute(assignment, memo),
+                b.substitute(assignment, memo),
+            ),
+            Select(addr, memory) => match addr.substitute(assignment, memo).as_const() {
+                Some(a) => read_cell(memory, a).substitute(assignment, memo),
+                None => self.clone(),
+            },
+        };
+
+        memo.insert(Rc::as_ptr(self), v.clone());
+        v
+    }
+
+    /// The expression as `constant + sum(coefficient * symbol)`, if it
+    /// has that form.
+    fn linear(&self) -> Option<(BTreeMap<Symbol, Intcode>, Intcode)> {
+        match self {
+            Const(n) => Some((BTreeMap::new(), *n)),
+            Sym(s) => Some((BTreeMap::from([(*s, 1)]), 0)),
+            Add(a, b) => {
+                let (mut terms, c) = a.linear()?;
+                let (other, d) = b.linear()?;
+                for (s, k) in other {
+                    let term = terms.entry(s).or_insert(0);
+                    *term = term.checked_add(k)?;
+                }
+                terms.retain(|_, k| *k != 0);
+                Some((terms, c.checked_add(d)?))
+            }
+            Mul(a, b) => {
+                let (terms, c, k) = match (a.as_const(), b.as_const()) {
+                    (Some(k), _) => {
+                        let (terms, c) = b.linear()?;
+                        (terms, c, k)
+                    }
+                    (_, Some(k)) => {
+                        let (terms, c) = a.linear()?;
+                        (terms, c, k)
+                    }
+                    _ => return None,
+                };
+                let terms = terms
+                    .into_iter()
+                    .map(|(s, x)| Some((s, x.checked_mul(k)?)))
+                    .collect::<Option<_>>()?;
+                Some((terms, c.checked_mul(k)?))
+            }
+            _ => None,
+        }
+    }
+
+    /// Renders the formula using the given symbol names.
+    pub fn render(&self, names: &[String]) -> String {
+        if let Some((terms, c)) = self.linear() {
+            let mut sb = String::new();
+            for (s, k) in terms {
+                if !sb.is_empty() {
+                    sb += " + ";
+                }
+                if k != 1 {
+                    write!(sb, "{}*", k).unwrap();
+                }
+                sb += &names[s];
+            }
+            if sb.is_empty() || c != 0 {
+                if !sb.is_empty() {
+                    sb += " + ";
+                }
+                write!(sb, "{}", c).unwrap();
+            }
+            return sb;
+        }
+
+        match self {
+            Add(a, b) => format!("({} + {})", a.render(names), b.render(names)),
+            Mul(a, b) => format!("({} * {})", a.render(names), b.render(names)),
+            Less(a, b) => format!("({} < {})", a.render(names), b.render(names)),
+            Equal(a, b) => format!("({} == {})", a.render(names), b.render(names)),
+            Select(a, _) => format!("mem[{}]", a.render(names)),
+            Const(_) | Sym(_) => unreachable!("constants and symbols are linear"),
+        }
+    }
+}
+
+fn read_cell(memory: &[Value], addr: Intcode) -> Value {
+    usize::try_from(addr)
+        .ok()
+        .and_then(|a| memory.get(a))
+        .cloned()
+        .unwrap_or_else(|| Expr::constant(0))
+}
+
+/// A condition that a path assumed: `cond` is nonzero iff `holds`.
+#[derive(Debug, Clone)]
+pub struct Constraint {
+    pub cond: Value,
+    pub holds: bool,
+}
+
+impl Constraint {
+    pub fn new(cond: Value, holds: bool) -> Self {
+        // `eq x, 0` is how Intcode spells "not"
+        if let Equal(a, b) = &*cond {
+            if b.as_const() == Some(0) && a.is_boolean() {
+                return Constraint::new(a.clone(), !holds);
+            }
+        }
+        Constraint { cond, holds }
+    }
+
+    fn is_satisfied_by(&self, value: Intcode) -> bool {
+        (value != 0) == self.holds
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathEnd {
+    Halted,
+    /// The program asked for more inputs than were declared.
+    NeedsInput,
+    StepLimit,
+    /// Execution depended on a symbolic address, instruction or jump
+    /// target.
+    Stuck(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Path {
+    pub constraints: Vec<Constraint>,
+    pub outputs: Vec<Value>,
+    pub memory: Vec<Value>,
+    pub end: PathEnd,
+}
+
+impl Path {
+    pub fn read(&self, addr: usize) -> Value {
+        read_cell(&self.memory, addr as Intcode)
+    }
+}
+
+#[derive(Clone)]
+struct State {
+    memory: Vec<Value>,
+    ip: Intcode,
+    relative_base: Intcode,
+    inputs_used: usize,
+    constraints: Vec<Constraint>,
+    outputs: Vec<Value>,
+    steps: usize,
+}
+
+impl State {
+    fn read(&self, addr: &Value) -> Value {
+        match addr.as_const() {
+            Some(a) => read_cell(&self.memory, a),
+            None => Expr::select(addr.clone(), Rc::new(self.memory.clone())),
+        }
+    }
+
+    fn write(&mut self, addr: Intcode, value: Value) -> Result<(), String> {
+        let addr = usize::try_from(addr).map_err(|_| format!("negative address {}", addr))?;
+        if addr >= self.memory.len() {
+            self.memory.resize(addr + 1, Expr::constant(0));
+        }
+        self.memory[addr] = value;
+        Ok(())
+    }
+
+    fn word(&self, offset: Intcode) -> Value {
+        read_cell(&self.memory, self.ip + offset)
+    }
+
+    fn concrete_word(&self, offset: Intcode) -> Result<Intcode, String> {
+        self.word(offset)
+            .as_const()
+            .ok_or_else(|| format!("symbolic instruction word at {}", self.ip + offset))
+    }
+
+    fn param_mode(&self, argno: u32) -> Result<Intcode, String> {
+        Ok(self.concrete_word(0)? / 10_i64.pow(argno + 2) % 10)
+    }
+
+    fn param(&self, argno: u32) -> Result<Value, String> {
+        let word = self.word(1 + argno as Intcode);
+        match self.param_mode(argno)? {
+            PARAM_TYPE_POSITION => Ok(self.read(&word)),
+            PARAM_TYPE_IMMEDIATE => Ok(word),
+            PARAM_TYPE_RELATIVE => {
+                Ok(self.read(&Expr::add(Expr::constant(self.relative_base), word)))
+            }
+            x => Err(format!("unknown parameter type {} at {}", x, self.ip)),
+        }
+    }
+
+    fn dest(&self, argno: u32) -> Result<Intcode, String> {
+        let word = self.concrete_word(1 + argno as Intcode)?;
+        match self.param_mode(argno)? {
+            PARAM_TYPE_POSITION => Ok(word),
+            PARAM_TYPE_RELATIVE => Ok(self.relative_base + word),
+            _ => Err(format!("bad destination parameter at {}", self.ip)),
+        }
+    }
+}
+
+struct SymbolInfo {
+    name: String,
+    domain: RangeInclusive<Intcode>,
+}
+
+pub struct SymbolicExecutor {
+    program: Vec<Intcode>,
+    symbols: Vec<SymbolInfo>,
+    memory_symbols: Vec<(usize, Symbol)>,
+    input_symbols: Vec<Symbol>,
+    max_steps: usize,
+    max_paths: usize,
+}
+
+impl SymbolicExecutor {
+    pub fn new(program: Vec<Intcode>) -> Self {
+        SymbolicExecutor {
+            program,
+            symbols: vec![],
+            memory_symbols: vec![],
+            input_symbols: vec![],
+            max_steps: DEFAULT_MAX_STEPS,
+            max_paths: DEFAULT_MAX_PATHS,
+        }
+    }
+
+    fn add_symbol(&mut self, name: &str, domain: RangeInclusive<Intcode>) -> Symbol {
+        self.symbols.push(SymbolInfo {
+            name: name.to_owned(),
+            domain,
+        });
+        self.symbols.len() - 1
+    }
+
+    /// Replaces the initial value at `addr` with a symbol.
+    pub fn memory_symbol(
+        &mut self,
+        addr: usize,
+        name: &str,
+        domain: RangeInclusive<Intcode>,
+    ) -> Symbol {
+        let s = self.add_symbol(name, domain);
+        self.memory_symbols.push((addr, s));
+        s
+    }
+
+    /// Declares the next value the program will read as input.
+    pub fn input_symbol(&mut self, name: &str, domain: RangeInclusive<Intcode>) -> Symbol {
+        let s = self.add_symbol(name, domain);
+        self.input_symbols.push(s);
+        s
+    }
+
+    pub fn max_steps(&mut self, max_steps: usize) {
+        self.max_steps = max_steps;
+    }
+
+    pub fn max_paths(&mut self, max_paths: usize) {
+        self.max_paths = max_paths;
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.symbols.iter().map(|s| s.name.clone()).collect()
+    }
+
+    /// Follows every feasible path through the program. Branches whose
+    /// constraints have no solution within the symbol domains are
+    /// dropped as soon as they are taken.
+    pub fn explore(&self) -> Vec<Path> {
+        let mut memory: Vec<Value> = self.program.iter().map(|&n| Expr::constant(n)).collect();
+        for &(addr, s) in &self.memory_symbols {
+            if addr >= memory.len() {
+                memory.resize(addr + 1, Expr::constant(0));
+            }
+            memory[addr] = Expr::symbol(s);
+        }
+
+        let mut pending = vec![State {
+            memory,
+            ip: 0,
+            relative_base: 0,
+            inputs_used: 0,
+            constraints: vec![],
+            outputs: vec![],
+            steps: 0,
+        }];
+        let mut paths = vec![];
+
+        while let Some(mut state) = pending.pop() {
+            let end = if paths.len() + pending.len() >= self.max_paths {
+                PathEnd::StepLimit
+            } else {
+                match self.run(&mut state, &mut pending) {
+                    Ok(end) => end,
+                    Err(reason) => PathEnd::Stuck(reason),
+                }
+            };
+
+            paths.push(Path {
+                constraints: state.constraints,
+                outputs: state.outputs,
+                memory: state.memory,
+                end,
+            });
+        }
+
+        paths
+    }
+
+    /// Runs one state until it ends, pushing the other side of every
+    /// feasible fork onto `pending`.
+    fn run(&self, state: &mut State, pending: &mut Vec<State>) -> Result<PathEnd, String> {
+        loop {
+            if state.steps == self.max_steps {
+                return Ok(PathEnd::StepLimit);
+            }
+            state.steps += 1;
+
+            let opcode = state.concrete_word(0)? % 100;
+            match opcode {
+                OP_ADD | OP_MUL | OP_LESS_THAN | OP_EQUALS => {
+                    let a = state.param(0)?;
+                    let b = state.param(1)?;
+                    let dest = state.dest(2)?;
+                    let value = match opcode {
+                        OP_ADD => Expr::add(a, b),
+                        OP_MUL => Expr::mul(a, b),
+                        OP_LESS_THAN => Expr::less(a, b),
+                        _ => Expr::equal(a, b),
+                    };
+                    state.write(dest, value)?;
+                    state.ip += 4;
+                }
+                OP_STORE_INPUT => {
+                    let Some(&s) = self.input_symbols.get(state.inputs_used) else {
+                        return Ok(PathEnd::NeedsInput);
+                    };
+                    state.inputs_used += 1;
+                    let dest = state.dest(0)?;
+                    state.write(dest, Expr::symbol(s))?;
+                    state.ip += 2;
+                }
+                OP_WRITE_OUTPUT => {
+                    let value = state.param(0)?;
+                    state.outputs.push(value);
+                    state.ip += 2;
+                }
+                OP_JUMP_IF_TRUE | OP_JUMP_IF_FALSE => {
+                    let cond = state.param(0)?;
+                    let target = state.param(1)?;
+                    let jump_when = opcode == OP_JUMP_IF_TRUE;
+
+                    let taken = match cond.as_const() {
+                        Some(c) => (c != 0) == jump_when,
+                        None => {
+                            let mut other = state.clone();
+                            other
+                                .constraints
+                                .push(Constraint::new(cond.clone(), !jump_when));
+                            other.ip += 3;
+                            if self.solve(&other.constraints).is_some() {
+                                pending.push(other);
+                            }
+
+                            state.constraints.push(Constraint::new(cond, jump_when));
+                            if self.solve(&state.constraints).is_none() {
+                                *state = pending.pop().expect("one side of a fork is feasible");
+                                continue;
+                            }
+                            true
+                        }
+                    };
+
+                    if taken {
+                        state.ip = target
+                            .as_const()
+                            .ok_or_else(|| format!("symbolic jump target at {}", state.ip))?;
+                    } else {
+                        state.ip += 3;
+                    }
+                }
+                OP_ADJUST_RELATIVE_BASE => {
+                    let delta = state.param(0)?;
+                    state.relative_base += delta
+                        .as_const()
+                        .ok_or_else(|| format!("symbolic relative base at {}", state.ip))?;
+                    state.ip += 2;
+                }
+                OP_HALT => return Ok(PathEnd::Halted),
+                x => return Err(format!("unknown opcode {} at {}", x, state.ip)),
+            }
+        }
+    }
+
+    /// Finds symbol values within their domains that satisfy all of
+    /// the constraints, indexed by symbol.
+    pub fn solve(&self, constraints: &[Constraint]) -> Option<Vec<Intcode>> {
+        let mut assignment = vec![None; self.symbols.len()];
+        if self.search(0, constraints.to_vec(), &mut assignment) {
+            Some(assignment.into_iter().map(Option::unwrap).collect())
+        } else {
+            None
+        }
+    }
+
+    /// Like `solve`, for the constraints of `path` plus a goal.
+    pub fn solve_path(&self, path: &Path, goal: Constraint) -> Option<Vec<Intcode>> {
+        let mut constraints = path.constraints.clone();
+        constraints.push(goal);
+        self.solve(&constraints)
+    }
+
+    /// Backtracking search, one symbol at a time. Before enumerating a
+    /// symbol's domain, a linear constraint in that symbol alone is
+    /// used to pin it to one value or narrow its range.
+    fn search(
+        &self,
+        s: Symbol,
+        constraints: Vec<Constraint>,
+        assignment: &mut [Option<Intcode>],
+    ) -> bool {
+        if s == self.symbols.len() {
+            return true;
+        }
+
+        let mut lo = *self.symbols[s].domain.start();
+        let mut hi = *self.symbols[s].domain.end();
+
+        for c in &constraints {
+            let (lhs, rhs, strict) = match &*c.cond {
+                Equal(a, b) if c.holds => (a, b, None),
+                Less(a, b) => (a, b, Some(c.holds)),
+                _ => continue,
+            };
+            let Some((terms, constant)) =
+                Expr::add(lhs.clone(), Expr::mul(rhs.clone(), Expr::constant(-1))).linear()
+            else {
+                continue;
+            };
+            if terms.len() != 1 || !terms.contains_key(&s) {
+                continue;
+            }
+
+            // k*x + constant compared with 0
+            let k = terms[&s];
+            match strict {
+                None if constant % k == 0 => {
+                    let x = -constant / k;
+                    lo = lo.max(x);
+                    hi = hi.min(x);
+                }
+                None => return false,
+                Some(holds) => {
+                    // k*x + constant < 0 when holds, >= 0 otherwise
+                    let (k, constant) = if holds {
+                        (k, constant)
+                    } else {
+                        (-k, -constant - 1)
+                    };
+                    let bound = div_floor(-constant - 1, k.abs());
+                    if k > 0 {
+                        hi = hi.min(bound);
+                    } else {
+                        lo = lo.max(-bound);
+                    }
+                }
+            }
+        }
+
+        for x in lo..=hi {
+            assignment[s] = Some(x);
+            let mut memo = HashMap::new();
+            let mut narrowed = Vec::with_capacity(constraints.len());
+            let mut ok = true;
+            for c in &constraints {
+                let cond = c.cond.substitute(assignment, &mut memo);
+                match cond.as_const() {
+                    Some(v) if !c.is_satisfied_by(v) => {
+                        ok = false;
+                        break;
+                    }
+                    Some(_) => (),
+                    None => narrowed.push(Constraint::new(cond, c.holds)),
+                }
+            }
+
+            if ok && self.search(s + 1, narrowed, assignment) {
+                return true;
+            }
+        }
+
+        assignment[s] = None;
+        false
+    }
+}
+
+fn div_floor(a: Intcode, b: Intcode) -> Intcode {
+    let q = a / b;
+    if (a % b != 0) && ((a < 0) != (b < 0)) {
+        q - 1
+    } else {
+        q
+    }
+}
@@ -1,92 +1,139 @@
 #![allow(dead_code, unused_variables)]
 
 mod computer;
+mod report;
+mod simulator;
+mod springscript;
+mod synth;
 use computer::Computer;
+use report::{Library, Report};
+use springscript::{Mode, Program};
 
 type AdventResult = usize;
 
 use std::fs;
+use std::path::Path;
+
+/// Every hull the droid has fallen on so far. Only `part1` and `part2`
+/// add to it.
+const LIBRARY: &str = "hulls.txt";
 
 fn input() -> String {
     fs::read_to_string("input.txt").expect("Can't find input.txt")
 }
 
-fn do_part1(input: &str) -> AdventResult {
+/// Runs `program` on the droid, returning the hull damage it reports
+/// or what it showed of its fall.
+fn run_droid(input: &str, program: &Program) -> Result<AdventResult, Report> {
     let initial = Computer::parse_program(input);
     let mut computer = Computer::new(initial);
-    computer.buffer_inputs(Computer::ascii_to_intcodes("\
-NOT A T
-NOT B J
-OR T J
-NOT C T
-OR T J
-AND D J
-WALK
-").into_iter());
+    computer.buffer_inputs(program.to_intcodes().into_iter());
     computer.start();
     assert!(computer.is_halted());
     let output: Vec<_> = computer.consume_output_buffer().collect();
 
     if let Some(&n) = output.last() {
         if n > 255 {
-            return n as usize;
-        } 
+            return Ok(n as usize);
+        }
     }
-        
-    println!("{}", Computer::intcodes_to_ascii(output.into_iter()));
-    panic!();
-}
 
-fn do_part2(input: &str) -> AdventResult {
-    let initial = Computer::parse_program(input);
-    let mut computer = Computer::new(initial);
-    computer.buffer_inputs(Computer::ascii_to_intcodes("\
-NOT A T
-NOT B J
-OR T J
-NOT C T
-OR T J
-AND D J
-NOT J T
-NOT T T
-AND E T
-OR H T
-AND T J
-RUN
-").into_iter());
-    computer.start();
-    assert!(computer.is_halted());
-    let output: Vec<_> = computer.consume_output_buffer().collect();
+    let output = Computer::intcodes_to_ascii(output.into_iter());
+    match report::parse(&output) {
+        Some(report) => Err(report),
+        None => panic!("{}", output),
+    }
+}
 
-    if let Some(&n) = output.last() {
-        if n > 255 {
-            return n as usize;
-        } 
+/// Learns a program starting from the hulls in the library at
+/// `library_path`, and adds any the droid falls on along the way.
+fn do_part(input: &str, mode: Mode, library_path: &Path) -> AdventResult {
+    let mut library = Library::load(library_path).unwrap_or_else(|e| panic!("{}", e));
+    let mut hulls = library.hulls(mode);
+    let learned = synth::learn(mode, &mut hulls, |p| {
+        run_droid(input, p).map_err(|report| report.hull)
+    });
+    for hull in hulls {
+        library
+            .record(library_path, mode, hull)
+            .unwrap_or_else(|e| panic!("{}", e));
     }
-        
-    println!("{}", Computer::intcodes_to_ascii(output.into_iter()));
-    panic!();
+
+    let (_, damage) = learned.unwrap_or_else(|e| panic!("{}", e));
+    damage
+}
+
+fn do_part1(input: &str, library_path: &Path) -> AdventResult {
+    do_part(input, Mode::Walk, library_path)
+}
+
+fn do_part2(input: &str, library_path: &Path) -> AdventResult {
+    do_part(input, Mode::Run, library_path)
 }
 
 fn part1() -> AdventResult {
-    do_part1(&input())
+    do_part1(&input(), Path::new(LIBRARY))
 }
 
 fn part2() -> AdventResult {
-    do_part2(&input())
+    do_part2(&input(), Path::new(LIBRARY))
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    
+
+    use std::path::PathBuf;
+    use std::process;
+
+    /// A copy of the library for one test to learn into, so the
+    /// tracked one is left alone.
+    struct ScratchLibrary(PathBuf);
+
+    impl ScratchLibrary {
+        fn new(name: &str) -> Self {
+            let file = format!("aoc-2019-21-{}-{}.txt", name, process::id());
+            let path = std::env::temp_dir().join(file);
+            fs::copy(LIBRARY, &path).unwrap();
+            ScratchLibrary(path)
+        }
+    }
+
+    impl Drop for ScratchLibrary {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
     #[test]
     fn part1_solution() {
-        assert_eq!(19355862, part1());
+        let library = ScratchLibrary::new("part1");
+        assert_eq!(19355862, do_part1(&input(), &library.0));
     }
 
     #[test]
     fn part2_solution() {
-        assert_eq!(1140470745, part2());
+        let library = ScratchLibrary::new("part2");
+        assert_eq!(1140470745, do_part2(&input(), &library.0));
+    }
+
+    #[test]
+    fn library_regressions() {
+        let library = Library::load(LIBRARY).unwrap();
+        for mode in [Mode::Walk, Mode::Run] {
+            let hulls = library.hulls(mode);
+            assert!(!hulls.is_empty());
+            let program = synth::synthesize(mode, &hulls).unwrap();
+            for hull in &hulls {
+                assert!(simulator::simulate(&program, hull).survived(), "{}", hull);
+            }
+        }
+    }
+
+    #[test]
+    fn report_matches_simulator() {
+        let program = springscript::parse("NOT A J\nWALK\n").unwrap();
+        let report = run_droid(&input(), &program).unwrap_err();
+        assert_eq!(report.outcome, simulator::simulate(&program, &report.hull));
     }
 }
@@ -0,0 +1,237 @@
+//! Reads the animation the droid prints when it falls into the hull,
+//! and keeps a library of every hull it has fallen on.
+//!
+//! ```text
+//! .................
+//! .....@...........
+//! .................
+//! #####.#..########
+//! ```
+//!
+//! Each frame is three rows of air over the hull, with the droid drawn
+//! as `@`. It walks along the lowest row of air and is higher up while
+//! jumping. The last frame shows it in the hole.
+//!
+//! The library is a text file with one hull per line, after the mode
+//! the droid was in:
+//!
+//! ```text
+//! WALK #####...#########
+//! RUN #####.#..########
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::simulator::{Hull, Outcome};
+use crate::springscript::Mode;
+
+const AIR_ROWS: usize = 3;
+
+/// What the droid showed of its fall.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub hull: Hull,
+    /// Where it jumped and fell, as the simulator would put it.
+    pub outcome: Outcome,
+}
+
+/// Parses the droid's output, if it fell.
+pub fn parse(output: &str) -> Option<Report> {
+    let (_, animation) = output.split_once("Didn't make it across:")?;
+    let lines: Vec<&str> = animation.lines().filter(|l| !l.is_empty()).collect();
+
+    let mut hull = None;
+    let mut jumps = vec![];
+    let mut fell_at = None;
+    let mut last_on_ground = None;
+
+    for frame in lines.chunks(AIR_ROWS + 1) {
+        let (ground, air) = frame.split_last().unwrap();
+        if air.len() != AIR_ROWS {
+            return None;
+        }
+
+        if let Some(x) = ground.find('@') {
+            fell_at = Some(x);
+            break;
+        }
+        hull.get_or_insert(ground.parse().ok()?);
+
+        let (height, x) = air
+            .iter()
+            .rev()
+            .enumerate()
+            .find_map(|(h, row)| row.find('@').map(|x| (h, x)))?;
+        match (height, last_on_ground) {
+            (0, _) => last_on_ground = Some(x),
+            (_, Some(from)) => {
+                jumps.push(from);
+                last_on_ground = None;
+            }
+            _ => (),
+        }
+    }
+
+    Some(Report {
+        hull: hull?,
+        outcome: Outcome {
+            jumps,
+            fell_at: Some(fell_at?),
+        },
+    })
+}
+
+#[derive(Debug)]
+pub enum LibraryError {
+    Io(io::Error),
+    /// A line that isn't a mode and a hull.
+    Parse(usize, String),
+}
+
+impl fmt::Display for LibraryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LibraryError::Io(e) => write!(f, "{}", e),
+            LibraryError::Parse(line, text) => write!(f, "line {}: bad hull {:?}", line, text),
+        }
+    }
+}
+
+impl std::error::Error for LibraryError {}
+
+impl From<io::Error> for LibraryError {
+    fn from(e: io::Error) -> Self {
+        LibraryError::Io(e)
+    }
+}
+
+/// Every hull the droid has fallen on, by mode.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Library {
+    hulls: BTreeMap<Mode, BTreeSet<Hull>>,
+}
+
+impl Library {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the library at `path`, which needn't exist yet.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LibraryError> {
+        match fs::read_to_string(path) {
+            Ok(text) => text.parse(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn hulls(&self, mode: Mode) -> BTreeSet<Hull> {
+        self.hulls.get(&mode).cloned().unwrap_or_default()
+    }
+
+    /// Adds `hull`, returning whether it's new.
+    pub fn insert(&mut self, mode: Mode, hull: Hull) -> bool {
+        self.hulls.entry(mode).or_default().insert(hull)
+    }
+
+    /// Adds `hull` and, if it's new, appends it to the file at `path`.
+    pub fn record(&mut self, path: impl AsRef<Path>, mode: Mode, hull: Hull) -> io::Result<bool> {
+        let line = format!("{} {}\n", mode, hull);
+        if !self.insert(mode, hull) {
+            return Ok(false);
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(true)
+    }
+}
+
+impl fmt::Display for Library {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (mode, hulls) in &self.hulls {
+            for hull in hulls {
+                writeln!(f, "{} {}", mode, hull)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for Library {
+    type Err = LibraryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut library = Library::new();
+        for (n, line) in (1..).zip(s.lines()) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let error = || LibraryError::Parse(n, line.to_owned());
+            let (mode, hull) = line.trim().split_once(' ').ok_or_else(error)?;
+            let mode = mode.parse().map_err(|_| error())?;
+            let hull = hull.parse().map_err(|_| error())?;
+            library.insert(mode, hull);
+        }
+        Ok(library)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FALL: &str = "Input instructions:\n\nWalking...\n\n\nDidn't make it across:\n\n\
+        .................\n.................\n@................\n#####.#..########\n\n\
+        .................\n.................\n.@...............\n#####.#..########\n\n\
+        .................\n.................\n..@..............\n#####.#..########\n\n\
+        .................\n.................\n...@.............\n#####.#..########\n\n\
+        .................\n.................\n....@............\n#####.#..########\n\n\
+        .................\n.....@...........\n.................\n#####.#..########\n\n\
+        ......@..........\n.................\n.................\n#####.#..########\n\n\
+        .................\n.......@.........\n.................\n#####.#..########\n\n\
+        .................\n.................\n........@........\n#####.#..########\n\n\
+        .................\n.................\n.................\n#####.#.@########\n\n";
+
+    #[test]
+    fn parse_fall() {
+        assert_eq!(
+            Some(Report {
+                hull: "#####.#..########".parse().unwrap(),
+                outcome: Outcome {
+                    jumps: vec![4],
+                    fell_at: Some(8),
+                },
+            }),
+            parse(FALL)
+        );
+        assert_eq!(None, parse("Input instructions:\n\nWalking...\n\n\n"));
+    }
+
+    #[test]
+    fn library_round_trip() {
+        let text = "WALK #####.#..########\nRUN #####...#########\nWALK #####...#########\n";
+        let mut library: Library = text.parse().unwrap();
+        assert_eq!(2, library.hulls(Mode::Walk).len());
+        assert_eq!(1, library.hulls(Mode::Run).len());
+        assert!(!library.insert(Mode::Run, "#####...#########".parse().unwrap()));
+
+        // sorted by mode, then hull
+        assert_eq!(
+            "WALK #####...#########\nWALK #####.#..########\nRUN #####...#########\n",
+            library.to_string()
+        );
+        assert_eq!(
+            Ok(library.clone()),
+            library.to_string().parse().map_err(|_| ())
+        );
+
+        assert!(matches!(
+            "JOG ###".parse::<Library>(),
+            Err(LibraryError::Parse(1, _))
+        ));
+    }
+}
@@ -0,0 +1,183 @@
+//! Runs springscript natively, so a program can be tried against a
+//! stretch of hull without booting the droid.
+//!
+//! A hull is written the way the droid draws it, `#` for ground and `.`
+//! for a hole, starting from the tile the droid stands on. The droid
+//! walks one tile at a time, or jumps four when `J` ends up true. It
+//! senses the ground ahead of it only while on the ground, and anything
+//! past the end of the hull is ground.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::springscript::{Op, Program, Register};
+
+pub const JUMP_LENGTH: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Hull(Vec<bool>);
+
+impl Hull {
+    pub fn is_ground(&self, x: usize) -> bool {
+        self.0.get(x).copied().unwrap_or(true)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl fmt::Display for Hull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for &ground in &self.0 {
+            write!(f, "{}", if ground { '#' } else { '.' })?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HullError(pub char);
+
+impl fmt::Display for HullError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unexpected {:?} in hull", self.0)
+    }
+}
+
+impl std::error::Error for HullError {}
+
+impl FromStr for Hull {
+    type Err = HullError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.chars()
+            .map(|c| match c {
+                '#' => Ok(true),
+                '.' => Ok(false),
+                c => Err(HullError(c)),
+            })
+            .collect::<Result<_, _>>()
+            .map(Hull)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    /// Where the droid was standing each time it jumped.
+    pub jumps: Vec<usize>,
+    /// The hole the droid fell into, if it didn't make it.
+    pub fell_at: Option<usize>,
+}
+
+impl Outcome {
+    pub fn survived(&self) -> bool {
+        self.fell_at.is_none()
+    }
+}
+
+/// Whether `program` jumps with the droid standing at `x`.
+pub fn jumps_at(program: &Program, hull: &Hull, x: usize) -> bool {
+    let mut registers = [false; Register::ALL.len()];
+    for r in Register::ALL {
+        match r.distance() {
+            Some(distance) if program.mode().can_read(r) => {
+                registers[r as usize] = hull.is_ground(x + distance);
+            }
+            _ => (),
+        }
+    }
+
+    for instr in program.instrs() {
+        let src = registers[instr.src as usize];
+        let dst = &mut registers[instr.dst as usize];
+        *dst = match instr.op {
+            Op::And => src && *dst,
+            Op::Or => src || *dst,
+            Op::Not => !src,
+        };
+    }
+
+    registers[Register::J as usize]
+}
+
+/// Walks the droid from the start of `hull` until it falls or passes
+/// the end.
+pub fn simulate(program: &Program, hull: &Hull) -> Outcome {
+    let mut outcome = Outcome {
+        jumps: vec![],
+        fell_at: None,
+    };
+
+    let mut x = 0;
+    while x < hull.len() {
+        if !hull.is_ground(x) {
+            outcome.fell_at = Some(x);
+            break;
+        }
+        if jumps_at(program, hull, x) {
+            outcome.jumps.push(x);
+            x += JUMP_LENGTH;
+        } else {
+            x += 1;
+        }
+    }
+
+    outcome
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::springscript;
+
+    fn hull(s: &str) -> Hull {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn jump_late() {
+        let program = springscript::parse("NOT A J\nWALK\n").unwrap();
+
+        let outcome = simulate(&program, &hull("#...######"));
+        assert_eq!(vec![0], outcome.jumps);
+        assert!(outcome.survived());
+
+        let outcome = simulate(&program, &hull("###.#..###"));
+        assert_eq!(vec![2], outcome.jumps);
+        assert_eq!(Some(6), outcome.fell_at);
+    }
+
+    #[test]
+    fn sensor_range() {
+        // Walking, the droid can't see that jumping at 2 lands it
+        // somewhere it can only fall from.
+        let h = hull("#####.#.##.#.####");
+
+        let walk = "NOT A T\nNOT B J\nOR T J\nNOT C T\nOR T J\nAND D J\nWALK\n";
+        let walk = springscript::parse(walk).unwrap();
+        let outcome = simulate(&walk, &h);
+        assert_eq!(vec![2], outcome.jumps);
+        assert_eq!(Some(7), outcome.fell_at);
+
+        let run = "NOT A T\nNOT B J\nOR T J\nNOT C T\nOR T J\nAND D J\nNOT J T\n\
+                   NOT T T\nAND E T\nOR H T\nAND T J\nRUN\n";
+        let run = springscript::parse(run).unwrap();
+        let outcome = simulate(&run, &h);
+        assert_eq!(vec![4, 9], outcome.jumps);
+        assert!(outcome.survived());
+    }
+
+    #[test]
+    fn hull_round_trip() {
+        assert_eq!("#.##", hull("#.##").to_string());
+        assert_eq!(Err(HullError('@')), "#@#".parse::<Hull>());
+        // past the end is ground
+        assert!(hull(".").is_ground(5));
+    }
+}
@@ -0,0 +1,448 @@
+//! Springscript, checked before the droid ever sees it.
+//!
+//! ```text
+//! NOT A T  # t = !a
+//! OR T J   # j = j | t
+//! WALK
+//! ```
+//!
+//! Anything after a `#` is a comment. The droid accepts at most 15
+//! instructions, only writes to `T` and `J`, and can only see four
+//! tiles ahead (`A`-`D`) when walking but nine (`A`-`I`) when running.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::computer::{Computer, Intcode};
+
+pub const MAX_INSTRUCTIONS: usize = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Register {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    T,
+    J,
+}
+
+impl Register {
+    pub const ALL: [Register; 11] = [
+        Register::A,
+        Register::B,
+        Register::C,
+        Register::D,
+        Register::E,
+        Register::F,
+        Register::G,
+        Register::H,
+        Register::I,
+        Register::T,
+        Register::J,
+    ];
+
+    /// How many tiles ahead a sensor register looks, `None` for `T`
+    /// and `J`.
+    pub fn distance(self) -> Option<usize> {
+        match self {
+            Register::T | Register::J => None,
+            r => Some(r as usize + 1),
+        }
+    }
+
+    pub fn is_writable(self) -> bool {
+        matches!(self, Register::T | Register::J)
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl FromStr for Register {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Register::ALL
+            .into_iter()
+            .find(|r| r.to_string() == s)
+            .ok_or(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Op {
+    And,
+    Or,
+    Not,
+}
+
+impl Op {
+    pub const ALL: [Op; 3] = [Op::And, Op::Or, Op::Not];
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Op::And => write!(f, "AND"),
+            Op::Or => write!(f, "OR"),
+            Op::Not => write!(f, "NOT"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SpringInstr {
+    pub op: Op,
+    pub src: Register,
+    pub dst: Register,
+}
+
+impl fmt::Display for SpringInstr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.op, self.src, self.dst)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Mode {
+    Walk,
+    Run,
+}
+
+impl Mode {
+    /// The farthest sensor the droid has in this mode.
+    pub fn range(self) -> usize {
+        match self {
+            Mode::Walk => 4,
+            Mode::Run => 9,
+        }
+    }
+
+    pub fn can_read(self, register: Register) -> bool {
+        register.distance().is_none_or(|d| d <= self.range())
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mode::Walk => write!(f, "WALK"),
+            Mode::Run => write!(f, "RUN"),
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "WALK" => Ok(Mode::Walk),
+            "RUN" => Ok(Mode::Run),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    UnknownOp(String),
+    UnknownRegister(String),
+    /// Expected and found operand counts.
+    Operands(usize, usize),
+    NotWritable(Register),
+    NotInMode(Register, Mode),
+    TooManyInstructions,
+    /// Neither `WALK` nor `RUN` at the end.
+    MissingMode,
+    AfterMode,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpringError {
+    pub line: usize,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for SpringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            ErrorKind::UnknownOp(op) => write!(f, "unknown instruction {}", op),
+            ErrorKind::UnknownRegister(r) => write!(f, "unknown register {}", r),
+            ErrorKind::Operands(expected, found) => {
+                write!(f, "expected {} registers, found {}", expected, found)
+            }
+            ErrorKind::NotWritable(r) => write!(f, "can't write to {}, only T and J", r),
+            ErrorKind::NotInMode(r, mode) => write!(f, "{} can't see {}", mode, r),
+            ErrorKind::TooManyInstructions => {
+                write!(f, "more than {} instructions", MAX_INSTRUCTIONS)
+            }
+            ErrorKind::MissingMode => write!(f, "expected WALK or RUN"),
+            ErrorKind::AfterMode => write!(f, "nothing may follow WALK or RUN"),
+        }
+    }
+}
+
+impl std::error::Error for SpringError {}
+
+/// A springscript program the droid will accept.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Program {
+    instrs: Vec<SpringInstr>,
+    mode: Mode,
+}
+
+impl Program {
+    /// Checks `instrs` against the droid's rules, counting lines from
+    /// the first instruction.
+    pub fn new(instrs: Vec<SpringInstr>, mode: Mode) -> Result<Self, Vec<SpringError>> {
+        let lines: Vec<_> = (1..).zip(instrs).collect();
+        let errors = validate(&lines, mode);
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(Program {
+            instrs: lines.into_iter().map(|(_, i)| i).collect(),
+            mode,
+        })
+    }
+
+    pub fn instrs(&self) -> &[SpringInstr] {
+        &self.instrs
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn to_intcodes(&self) -> Vec<Intcode> {
+        Computer::ascii_to_intcodes(&self.to_string())
+    }
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for instr in &self.instrs {
+            writeln!(f, "{}", instr)?;
+        }
+        writeln!(f, "{}", self.mode)
+    }
+}
+
+impl FromStr for Program {
+    type Err = Vec<SpringError>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse(s)
+    }
+}
+
+/// Parses and validates springscript, reporting every error found.
+pub fn parse(source: &str) -> Result<Program, Vec<SpringError>> {
+    let mut errors = vec![];
+    let mut instrs = vec![];
+    let mut mode = None;
+    let mut last_line = 0;
+
+    for (line, text) in (1..).zip(source.lines()) {
+        last_line = line;
+        let text = text.split('#').next().unwrap();
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let Some((&op, operands)) = words.split_first() else {
+            continue;
+        };
+        let error = |kind| SpringError { line, kind };
+
+        if mode.is_some() {
+            errors.push(error(ErrorKind::AfterMode));
+            continue;
+        }
+
+        let op = match op {
+            "AND" => Op::And,
+            "OR" => Op::Or,
+            "NOT" => Op::Not,
+            "WALK" | "RUN" if !operands.is_empty() => {
+                errors.push(error(ErrorKind::Operands(0, operands.len())));
+                continue;
+            }
+            "WALK" => {
+                mode = Some(Mode::Walk);
+                continue;
+            }
+            "RUN" => {
+                mode = Some(Mode::Run);
+                continue;
+            }
+            op => {
+                errors.push(error(ErrorKind::UnknownOp(op.to_owned())));
+                continue;
+            }
+        };
+
+        let &[src, dst] = operands else {
+            errors.push(error(ErrorKind::Operands(2, operands.len())));
+            continue;
+        };
+        let mut register = |r: &str| {
+            let parsed = r.parse().ok();
+            if parsed.is_none() {
+                errors.push(error(ErrorKind::UnknownRegister(r.to_owned())));
+            }
+            parsed
+        };
+        if let (Some(src), Some(dst)) = (register(src), register(dst)) {
+            instrs.push((line, SpringInstr { op, src, dst }));
+        }
+    }
+
+    let Some(mode) = mode else {
+        errors.push(SpringError {
+            line: last_line + 1,
+            kind: ErrorKind::MissingMode,
+        });
+        return Err(errors);
+    };
+
+    errors.extend(validate(&instrs, mode));
+    if !errors.is_empty() {
+        errors.sort_by_key(|e| e.line);
+        return Err(errors);
+    }
+
+    Ok(Program {
+        instrs: instrs.into_iter().map(|(_, i)| i).collect(),
+        mode,
+    })
+}
+
+fn validate(instrs: &[(usize, SpringInstr)], mode: Mode) -> Vec<SpringError> {
+    let mut errors = vec![];
+
+    for (n, &(line, instr)) in instrs.iter().enumerate() {
+        let error = |kind| SpringError { line, kind };
+        if n == MAX_INSTRUCTIONS {
+            errors.push(error(ErrorKind::TooManyInstructions));
+        }
+        if !mode.can_read(instr.src) {
+            errors.push(error(ErrorKind::NotInMode(instr.src, mode)));
+        }
+        if !instr.dst.is_writable() {
+            errors.push(error(ErrorKind::NotWritable(instr.dst)));
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_program() {
+        let program = parse("NOT A T  # t = !a\n\nOR T J\nAND D J\nWALK\n").unwrap();
+        assert_eq!(Mode::Walk, program.mode());
+        assert_eq!(
+            &[
+                SpringInstr {
+                    op: Op::Not,
+                    src: Register::A,
+                    dst: Register::T,
+                },
+                SpringInstr {
+                    op: Op::Or,
+                    src: Register::T,
+                    dst: Register::J,
+                },
+                SpringInstr {
+                    op: Op::And,
+                    src: Register::D,
+                    dst: Register::J,
+                },
+            ],
+            program.instrs()
+        );
+        assert_eq!("NOT A T\nOR T J\nAND D J\nWALK\n", program.to_string());
+        assert_eq!(Ok(program.clone()), program.to_string().parse());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let errors =
+            parse("NOT A\nXOR A J\nAND Q J\nOR H J\nNOT J D\nWALK\nNOT A J\n").unwrap_err();
+        assert_eq!(
+            vec![
+                SpringError {
+                    line: 1,
+                    kind: ErrorKind::Operands(2, 1),
+                },
+                SpringError {
+                    line: 2,
+                    kind: ErrorKind::UnknownOp("XOR".to_owned()),
+                },
+                SpringError {
+                    line: 3,
+                    kind: ErrorKind::UnknownRegister("Q".to_owned()),
+                },
+                SpringError {
+                    line: 4,
+                    kind: ErrorKind::NotInMode(Register::H, Mode::Walk),
+                },
+                SpringError {
+                    line: 5,
+                    kind: ErrorKind::NotWritable(Register::D),
+                },
+                SpringError {
+                    line: 7,
+                    kind: ErrorKind::AfterMode,
+                },
+            ],
+            errors
+        );
+        assert_eq!("line 4: WALK can't see H", errors[3].to_string());
+
+        // H is fine when running
+        assert!(parse("OR H J\nRUN\n").is_ok());
+
+        assert_eq!(
+            vec![SpringError {
+                line: 2,
+                kind: ErrorKind::MissingMode,
+            }],
+            parse("NOT A J\n").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn instruction_limit() {
+        let fifteen = "NOT A J\n".repeat(MAX_INSTRUCTIONS);
+        assert!(parse(&format!("{}WALK\n", fifteen)).is_ok());
+
+        assert_eq!(
+            vec![SpringError {
+                line: 16,
+                kind: ErrorKind::TooManyInstructions,
+            }],
+            parse(&format!("{}NOT B J\nWALK\n", fifteen)).unwrap_err()
+        );
+
+        let instr = SpringInstr {
+            op: Op::Not,
+            src: Register::A,
+            dst: Register::J,
+        };
+        assert!(Program::new(vec![instr; 15], Mode::Walk).is_ok());
+        assert!(Program::new(vec![instr; 16], Mode::Walk).is_err());
+    }
+}
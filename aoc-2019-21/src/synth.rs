@@ -0,0 +1,575 @@
+//! Finds springscript that gets the droid across a set of hulls.
+//!
+//! Programs are searched in conjunctive normal form, the shape the
+//! hand-written ones had:
+//!
+//! ```text
+//! J = (!A | !B | !C) & D & (E | H)
+//! ```
+//!
+//! The first clause is built in `J` and each later one in `T`, then
+//! ANDed into `J`.
+//!
+//! The search keeps a list of sensor readings the droid must jump at
+//! and ones it must walk past, and finds the shortest program that
+//! agrees with them by picking clauses that rule out every reading it
+//! mustn't jump at. That program is run over the hulls, and if the
+//! droid falls, one of the choices it made on the way must change, so
+//! each is tried as a further constraint. Constraints only ever make
+//! programs longer, so taking the shortest candidate each time means
+//! the first program that clears every hull is as short as this shape
+//! allows.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet};
+use std::fmt;
+
+use crate::simulator::{self, Hull, JUMP_LENGTH};
+use crate::springscript::{Mode, Op, Program, Register, SpringInstr, MAX_INSTRUCTIONS};
+
+/// Literals per clause. Enough for `!A | !B | !C`, and each one more
+/// makes the search a few times slower.
+const MAX_CLAUSE_SIZE: usize = 3;
+
+/// What the droid's sensors read, one bit per tile, `A` lowest.
+type Window = u16;
+
+/// A set of distinct windows, enough for nine sensors.
+type Table = [u64; 8];
+
+fn get(table: &Table, i: usize) -> bool {
+    table[i / 64] & 1 << (i % 64) != 0
+}
+
+fn set(table: &mut Table, i: usize) {
+    table[i / 64] |= 1 << (i % 64);
+}
+
+/// `!(p1 & p2 & ...) | q1 | q2 | ...`, sensors as bits like a
+/// `Window`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Clause {
+    negated: Window,
+    plain: Window,
+}
+
+impl Clause {
+    fn eval(&self, window: Window) -> bool {
+        window & self.negated != self.negated || window & self.plain != 0
+    }
+
+    /// Builds the clause in `dst`, overwriting whatever was there.
+    fn compile(&self, dst: Register, instrs: &mut Vec<SpringInstr>) {
+        let mut emit = |op, src| instrs.push(SpringInstr { op, src, dst });
+        let negated = sensors(self.negated);
+        let mut plain = sensors(self.plain).into_iter();
+
+        match negated[..] {
+            [] => {
+                emit(Op::Not, plain.next().unwrap());
+                emit(Op::Not, dst);
+            }
+            [p] => emit(Op::Not, p),
+            [p, ref rest @ ..] => {
+                emit(Op::Not, p);
+                emit(Op::Not, dst);
+                for &p in rest {
+                    emit(Op::And, p);
+                }
+                emit(Op::Not, dst);
+            }
+        }
+        for q in plain {
+            emit(Op::Or, q);
+        }
+    }
+
+    /// Instructions to build the clause in `J` from scratch.
+    fn first_cost(&self) -> usize {
+        let mut instrs = vec![];
+        self.compile(Register::J, &mut instrs);
+        instrs.len()
+    }
+
+    /// Instructions to AND the clause into `J`.
+    fn rest_cost(&self) -> usize {
+        if self.negated == 0 && self.plain.count_ones() == 1 {
+            1
+        } else {
+            self.first_cost() + 1
+        }
+    }
+
+    fn and_into_j(&self, instrs: &mut Vec<SpringInstr>) {
+        if self.negated == 0 && self.plain.count_ones() == 1 {
+            instrs.push(SpringInstr {
+                op: Op::And,
+                src: sensors(self.plain)[0],
+                dst: Register::J,
+            });
+        } else {
+            self.compile(Register::T, instrs);
+            instrs.push(SpringInstr {
+                op: Op::And,
+                src: Register::T,
+                dst: Register::J,
+            });
+        }
+    }
+}
+
+fn sensors(bits: Window) -> Vec<Register> {
+    Register::ALL
+        .into_iter()
+        .enumerate()
+        .filter(|&(i, _)| bits & 1 << i != 0)
+        .map(|(_, r)| r)
+        .collect()
+}
+
+/// Every clause over the first `range` sensors, with at most
+/// `MAX_CLAUSE_SIZE` literals.
+fn clauses(range: usize) -> Vec<Clause> {
+    let mut clauses = vec![];
+    for negated in 0..(1 as Window) << range {
+        for plain in 0..1 << range {
+            let size = (negated | plain).count_ones() as usize;
+            if negated & plain == 0 && size > 0 && size <= MAX_CLAUSE_SIZE {
+                clauses.push(Clause { negated, plain });
+            }
+        }
+    }
+    clauses
+}
+
+/// Instructions for a program made of `clauses`, putting first the
+/// one that's cheapest to build straight into `J`.
+fn program_cost(clauses: &[&Candidate]) -> usize {
+    let rest: usize = clauses.iter().map(|c| c.rest_cost).sum();
+    let saving = clauses
+        .iter()
+        .map(|c| c.rest_cost as isize - c.first_cost as isize)
+        .max()
+        .unwrap_or(0);
+    (rest as isize - saving) as usize
+}
+
+fn program(mode: Mode, clauses: &[&Candidate]) -> Program {
+    let mut clauses = clauses.to_vec();
+    let best_first = (0..clauses.len())
+        .max_by_key(|&i| clauses[i].rest_cost as isize - clauses[i].first_cost as isize)
+        .unwrap();
+    clauses.swap(0, best_first);
+
+    let mut instrs = vec![];
+    clauses[0].clause.compile(Register::J, &mut instrs);
+    for c in &clauses[1..] {
+        c.clause.and_into_j(&mut instrs);
+    }
+    Program::new(instrs, mode).expect("synthesized an invalid program")
+}
+
+fn window(hull: &Hull, x: usize, range: usize) -> Window {
+    (0..range)
+        .filter(|&i| hull.is_ground(x + 1 + i))
+        .fold(0, |w, i| w | 1 << i)
+}
+
+/// The hulls boiled down to which window the droid sees where.
+struct Course {
+    windows: Vec<Window>,
+    /// Per hull, the index into `windows` for each tile.
+    lookup: Vec<Vec<usize>>,
+    hulls: Vec<Hull>,
+}
+
+impl Course {
+    fn new<'a>(hulls: impl IntoIterator<Item = &'a Hull>, range: usize) -> Self {
+        let mut index = HashMap::new();
+        let mut windows = vec![];
+        let mut lookup = vec![];
+        let hulls: Vec<Hull> = hulls.into_iter().cloned().collect();
+
+        for hull in &hulls {
+            lookup.push(
+                (0..hull.len())
+                    .map(|x| {
+                        let w = window(hull, x, range);
+                        *index.entry(w).or_insert_with(|| {
+                            windows.push(w);
+                            windows.len() - 1
+                        })
+                    })
+                    .collect(),
+            );
+        }
+
+        Course {
+            windows,
+            lookup,
+            hulls,
+        }
+    }
+
+    /// The windows where `clause` is false.
+    fn falsified(&self, clause: &Clause) -> Table {
+        let mut table = [0; 8];
+        for (i, &w) in self.windows.iter().enumerate() {
+            if !clause.eval(w) {
+                set(&mut table, i);
+            }
+        }
+        table
+    }
+
+    /// Follows the droid over each hull, jumping at the windows in
+    /// `jump`, and returns the choices it made on the first hull it
+    /// falls on, as `(window, jumped)`.
+    fn fall(&self, jump: &Table) -> Option<Vec<(usize, bool)>> {
+        for (hull, lookup) in self.hulls.iter().zip(&self.lookup) {
+            let mut choices = vec![];
+            let mut x = 0;
+            while x < hull.len() {
+                if !hull.is_ground(x) {
+                    return Some(choices);
+                }
+                let jumped = get(jump, lookup[x]);
+                choices.push((lookup[x], jumped));
+                x += if jumped { JUMP_LENGTH } else { 1 };
+            }
+        }
+        None
+    }
+}
+
+/// Windows the droid has to jump at, and ones it has to walk past.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Constraints {
+    jump: Table,
+    walk: Table,
+}
+
+/// A clause along with the windows it rules out jumping at.
+struct Candidate {
+    clause: Clause,
+    falsified: Table,
+    first_cost: usize,
+    rest_cost: usize,
+}
+
+/// The shortest set of clauses that is true at every window in
+/// `constraints.jump` and false, between them, at every window in
+/// `constraints.walk`. Walk windows are tracked in a `u128`, so there's
+/// no answer for more than 128 of them.
+fn cover<'a>(all: &'a [Candidate], constraints: &Constraints) -> Option<Vec<&'a Candidate>> {
+    let ones = |table: &Table| (0..table.len() * 64).filter(|&i| get(table, i)).collect();
+    let jump: Vec<usize> = ones(&constraints.jump);
+    let walk: Vec<usize> = ones(&constraints.walk);
+    if walk.len() > 128 {
+        return None;
+    }
+
+    // Only what a clause rules out among the walk windows matters, and
+    // a clause is no use if another rules out as much for no more.
+    let mut candidates: Vec<(u128, &Candidate)> = vec![];
+    let mut seen = HashSet::new();
+    for c in all {
+        if jump.iter().any(|&w| get(&c.falsified, w)) {
+            continue;
+        }
+        let mask = (0..walk.len())
+            .filter(|&i| get(&c.falsified, walk[i]))
+            .fold(0, |m, i| m | 1 << i);
+        if seen.insert((mask, c.first_cost, c.rest_cost)) {
+            candidates.push((mask, c));
+        }
+    }
+    let dominates = |(a, ca): &(u128, &Candidate), (b, cb): &(u128, &Candidate)| {
+        b & !a == 0 && ca.rest_cost <= cb.rest_cost && ca.first_cost <= cb.first_cost
+    };
+    let candidates: Vec<(u128, &Candidate)> = candidates
+        .iter()
+        .enumerate()
+        .filter(|&(i, b)| {
+            !candidates
+                .iter()
+                .enumerate()
+                .any(|(j, a)| j != i && dominates(a, b) && (!dominates(b, a) || j < i))
+        })
+        .map(|(_, &c)| c)
+        .collect();
+
+    let mut best = None;
+    let mut best_cost = MAX_INSTRUCTIONS + 1;
+    if walk.is_empty() {
+        // Anything true where it has to jump will do.
+        for &(_, c) in &candidates {
+            if c.first_cost < best_cost {
+                best_cost = c.first_cost;
+                best = Some(vec![c]);
+            }
+        }
+        return best;
+    }
+
+    fn search<'a>(
+        left: u128,
+        candidates: &[(u128, &'a Candidate)],
+        chosen: &mut Vec<&'a Candidate>,
+        best: &mut Option<Vec<&'a Candidate>>,
+        best_cost: &mut usize,
+    ) {
+        // Building the first clause in J saves at most one instruction.
+        let cost: usize = chosen.iter().map(|c| c.rest_cost).sum();
+        if cost > *best_cost {
+            return;
+        }
+
+        if left == 0 {
+            let cost = program_cost(chosen);
+            if cost < *best_cost {
+                *best_cost = cost;
+                *best = Some(chosen.clone());
+            }
+            return;
+        }
+
+        let window = left & left.wrapping_neg();
+        for &(mask, c) in candidates.iter().filter(|(m, _)| m & window != 0) {
+            chosen.push(c);
+            search(left & !mask, candidates, chosen, best, best_cost);
+            chosen.pop();
+        }
+    }
+
+    let left = if walk.len() == 128 {
+        !0
+    } else {
+        (1 << walk.len()) - 1
+    };
+    search(left, &candidates, &mut vec![], &mut best, &mut best_cost);
+    best
+}
+
+/// The shortest program, in the shape described above, that survives
+/// every one of `hulls`.
+pub fn synthesize<'a>(mode: Mode, hulls: impl IntoIterator<Item = &'a Hull>) -> Option<Program> {
+    let course = Course::new(hulls, mode.range());
+
+    // Only one clause of each behaviour is worth considering.
+    let mut all: Vec<Candidate> = vec![];
+    let mut seen = HashSet::new();
+    for clause in clauses(mode.range()) {
+        let falsified = course.falsified(&clause);
+        let (first_cost, rest_cost) = (clause.first_cost(), clause.rest_cost());
+        if seen.insert((falsified, first_cost, rest_cost)) {
+            all.push(Candidate {
+                clause,
+                falsified,
+                first_cost,
+                rest_cost,
+            });
+        }
+    }
+
+    // Cheapest first, and among those the most recently found, which
+    // tends to be the most constrained. A node's cover is only worked
+    // out when it comes up, queued until then under its parent's
+    // length, since more constraints never make a program shorter.
+    let mut nodes: Vec<(Constraints, Option<Vec<&Candidate>>)> = vec![];
+    let mut queue = BinaryHeap::new();
+    let mut visited = HashSet::new();
+
+    let start = Constraints {
+        jump: [0; 8],
+        walk: [0; 8],
+    };
+    queue.push((Reverse(0), 0));
+    nodes.push((start, None));
+
+    while let Some((Reverse(bound), i)) = queue.pop() {
+        let (constraints, clauses) = nodes[i].clone();
+        let clauses = match clauses {
+            Some(clauses) => clauses,
+            None => {
+                let Some(clauses) = cover(&all, &constraints) else {
+                    continue;
+                };
+                let cost = program_cost(&clauses);
+                if cost > bound {
+                    nodes[i].1 = Some(clauses);
+                    queue.push((Reverse(cost), i));
+                    continue;
+                }
+                clauses
+            }
+        };
+
+        let mut jump = [0; 8];
+        for (i, &w) in course.windows.iter().enumerate() {
+            if clauses.iter().all(|c| c.clause.eval(w)) {
+                set(&mut jump, i);
+            }
+        }
+
+        let Some(choices) = course.fall(&jump) else {
+            return Some(program(mode, &clauses));
+        };
+
+        // Something along the way has to be done differently.
+        for (window, jumped) in choices {
+            let mut constraints = constraints.clone();
+            if get(&constraints.jump, window) || get(&constraints.walk, window) {
+                continue;
+            }
+            set(
+                if jumped {
+                    &mut constraints.walk
+                } else {
+                    &mut constraints.jump
+                },
+                window,
+            );
+            if visited.insert(constraints.clone()) {
+                queue.push((Reverse(program_cost(&clauses)), nodes.len()));
+                nodes.push((constraints, None));
+            }
+        }
+    }
+
+    None
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SynthError {
+    /// Nothing within the instruction limit clears every hull.
+    NoProgram,
+    /// The droid fell on a hull the program was already meant to
+    /// clear, so the simulator and the droid disagree.
+    Disagreement(Program, Hull),
+}
+
+impl fmt::Display for SynthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SynthError::NoProgram => write!(f, "no springscript clears every hull"),
+            SynthError::Disagreement(program, hull) => {
+                write!(f, "droid fell on {} running\n{}", hull, program)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SynthError {}
+
+/// Synthesizes programs and tries them on the droid until one gets
+/// across. Every hull the droid falls on is added to `hulls` before
+/// the next try. `attempt` runs a program on the droid, returning
+/// whatever it reports on success or the hull it fell on.
+pub fn learn<T>(
+    mode: Mode,
+    hulls: &mut BTreeSet<Hull>,
+    mut attempt: impl FnMut(&Program) -> Result<T, Hull>,
+) -> Result<(Program, T), SynthError> {
+    loop {
+        let program = synthesize(mode, hulls.iter()).ok_or(SynthError::NoProgram)?;
+        debug_assert!(hulls
+            .iter()
+            .all(|h| simulator::simulate(&program, h).survived()));
+
+        match attempt(&program) {
+            Ok(report) => return Ok((program, report)),
+            Err(hull) => {
+                if !hulls.insert(hull.clone()) {
+                    return Err(SynthError::Disagreement(program, hull));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn hulls(s: &[&str]) -> Vec<Hull> {
+        s.iter().map(|h| h.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn clause_compiles() {
+        for clause in clauses(4) {
+            let mut instrs = vec![];
+            clause.compile(Register::J, &mut instrs);
+            assert_eq!(clause.first_cost(), instrs.len());
+
+            let program = Program::new(instrs, Mode::Walk).unwrap();
+            for w in 0..16 {
+                let ahead: String = (0..4)
+                    .map(|i| if w & 1 << i != 0 { '#' } else { '.' })
+                    .collect();
+                let hull: Hull = format!("#{}", ahead).parse().unwrap();
+                assert_eq!(
+                    clause.eval(w),
+                    simulator::jumps_at(&program, &hull, 0),
+                    "{:?} {:04b}",
+                    clause,
+                    w
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn cover_gives_up_on_too_many_walk_windows() {
+        let mut constraints = Constraints {
+            jump: [0; 8],
+            walk: [0; 8],
+        };
+        for i in 0..129 {
+            set(&mut constraints.walk, i);
+        }
+        assert!(cover(&[], &constraints).is_none());
+    }
+
+    #[test]
+    fn synthesize_walk() {
+        let hulls = hulls(&["###.######", "#...######", "###.#..###", "##.#.#####"]);
+        let program = synthesize(Mode::Walk, &hulls).unwrap();
+        for hull in &hulls {
+            assert!(simulator::simulate(&program, hull).survived(), "{}", hull);
+        }
+        assert!(program.instrs().len() <= 6);
+    }
+
+    #[test]
+    fn learn_from_falls() {
+        // A stand-in for the droid that knows every hull it will be
+        // tested on.
+        let course = hulls(&[
+            "#####.#.##.#.####",
+            "#####.##.##.#.###",
+            "#####..#.########",
+            "#####.#..########",
+            "#####...#########",
+        ]);
+        let mut seen = BTreeSet::new();
+        let mut tries = 0;
+        let (program, ()) = learn(Mode::Run, &mut seen, |program| {
+            tries += 1;
+            match course
+                .iter()
+                .find(|h| !simulator::simulate(program, h).survived())
+            {
+                Some(hull) => Err(hull.clone()),
+                None => Ok(()),
+            }
+        })
+        .unwrap();
+
+        assert_eq!(tries, seen.len() + 1);
+        for hull in &course {
+            assert!(simulator::simulate(&program, hull).survived(), "{}", hull);
+        }
+    }
+}
@@ -0,0 +1,994 @@
+#![allow(dead_code)]
+
+//! A compiler from a tiny C-like language to Intcode.
+//!
+//! ```text
+//! fn main() {
+//!     let n = input();
+//!     let i = 0;
+//!     while i < n {
+//!         output(fib(i));
+//!         i = i + 1;
+//!     }
+//! }
+//!
+//! fn fib(n) {
+//!     if n < 2 {
+//!         return n;
+//!     }
+//!     return fib(n - 1) + fib(n - 2);
+//! }
+//! ```
+//!
+//! All values are integers. Expressions support `+ - *`, the
+//! comparisons `< <= > >= == !=`, `!`, unary `-` and short-circuit
+//! `&&`/`||`, and the builtins `input()` and `output(e)`. Execution
+//! starts at `main`, which takes no parameters.
+//!
+//! Each function call gets a frame on a stack that starts right after
+//! the code, and the relative base always points at the current
+//! frame:
+//!
+//! ```text
+//! [rb+0]       return address
+//! [rb+1]       return value, shared with the first parameter
+//! [rb+1..]     parameters
+//! [rb+..]      locals, then expression temporaries
+//! ```
+//!
+//! The caller writes the return address and arguments just past its
+//! own frame, moves the relative base there and jumps. The callee
+//! returns by jumping through `[rb+0]`, and the caller moves the
+//! relative base back.
+
+use crate::computer::{
+    Intcode, OP_ADD, OP_ADJUST_RELATIVE_BASE, OP_EQUALS, OP_HALT, OP_JUMP_IF_FALSE,
+    OP_JUMP_IF_TRUE, OP_LESS_THAN, OP_MUL, OP_PARAMETER_BASE, OP_PARAMETER_BASE_POS,
+    OP_STORE_INPUT, OP_WRITE_OUTPUT, PARAM_TYPE_IMMEDIATE, PARAM_TYPE_RELATIVE,
+};
+
+use std::collections::HashMap;
+use std::fmt;
+
+const SLOT_RETURN_ADDR: Intcode = 0;
+const SLOT_RETURN_VALUE: Intcode = 1;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CompileError {
+    pub line: usize,
+    pub col: usize,
+    pub message: String,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.col, self.message)
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Span {
+    line: usize,
+    col: usize,
+}
+
+impl Span {
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, CompileError> {
+        Err(CompileError {
+            line: self.line,
+            col: self.col,
+            message: message.into(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Number(Intcode),
+    Ident(String),
+    Punct(&'static str),
+    Eof,
+}
+
+const PUNCTUATION: [&str; 19] = [
+    "&&", "||", "==", "!=", "<=", ">=", "(", ")", "{", "}", ",", ";", "=", "+", "-", "*", "<", ">",
+    "!",
+];
+
+fn tokenize(source: &str) -> Result<Vec<(Token, Span)>, CompileError> {
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut line_start = 0;
+    let mut i = 0;
+    let bytes = source.as_bytes();
+
+    while i < bytes.len() {
+        let span = Span {
+            line,
+            col: i - line_start + 1,
+        };
+        let rest = &source[i..];
+        let c = bytes[i];
+
+        if c == b'\n' {
+            line += 1;
+            line_start = i + 1;
+            i += 1;
+        } else if c.is_ascii_whitespace() {
+            i += 1;
+        } else if rest.starts_with("//") {
+            i += rest.find('\n').unwrap_or(rest.len());
+        } else if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let Ok(n) = rest[..len].parse() else {
+                return span.error(format!("number `{}` is too large", &rest[..len]));
+            };
+            tokens.push((Token::Number(n), span));
+            i += len;
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            let len = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push((Token::Ident(rest[..len].to_owned()), span));
+            i += len;
+        } else if let Some(p) = PUNCTUATION.iter().find(|p| rest.starts_with(*p)) {
+            tokens.push((Token::Punct(p), span));
+            i += p.len();
+        } else {
+            return span.error(format!(
+                "unexpected character `{}`",
+                rest.chars().next().unwrap()
+            ));
+        }
+    }
+
+    let span = Span {
+        line,
+        col: i - line_start + 1,
+    };
+    tokens.push((Token::Eof, span));
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Less,
+    LessEq,
+    Greater,
+    GreaterEq,
+    Equal,
+    NotEqual,
+    And,
+    Or,
+}
+
+#[derive(Debug)]
+enum Expr {
+    Number(Intcode),
+    Var(String, Span),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>, Span),
+}
+
+#[derive(Debug)]
+enum Stmt {
+    Let(String, Expr, Span),
+    Assign(String, Expr, Span),
+    If(Expr, Vec<Stmt>, Vec<Stmt>),
+    While(Expr, Vec<Stmt>),
+    Return(Option<Expr>),
+    Expr(Expr),
+}
+
+#[derive(Debug)]
+struct Function {
+    name: String,
+    params: Vec<String>,
+    body: Vec<Stmt>,
+    span: Span,
+}
+
+struct Parser {
+    tokens: Vec<(Token, Span)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn span(&self) -> Span {
+        self.tokens[self.pos].1
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.pos].0.clone();
+        if token != Token::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        if matches!(self.peek(), Token::Punct(p) if *p == punct) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), CompileError> {
+        if self.eat(punct) {
+            Ok(())
+        } else {
+            self.span().error(format!(
+                "expected `{}`, found {}",
+                punct,
+                describe(self.peek())
+            ))
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), CompileError> {
+        if self.is_keyword(keyword) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            self.span().error(format!(
+                "expected `{}`, found {}",
+                keyword,
+                describe(self.peek())
+            ))
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Token::Ident(s) if s == keyword)
+    }
+
+    fn ident(&mut self) -> Result<(String, Span), CompileError> {
+        let span = self.span();
+        match self.advance() {
+            Token::Ident(s) if !is_keyword(&s) => Ok((s, span)),
+            t => span.error(format!("expected a name, found {}", describe(&t))),
+        }
+    }
+
+    fn program(&mut self) -> Result<Vec<Function>, CompileError> {
+        let mut functions = vec![];
+        while *self.peek() != Token::Eof {
+            functions.push(self.function()?);
+        }
+        Ok(functions)
+    }
+
+    fn function(&mut self) -> Result<Function, CompileError> {
+        self.expect_keyword("fn")?;
+        let (name, span) = self.ident()?;
+        self.expect("(")?;
+        let mut params = vec![];
+        if !self.eat(")") {
+            loop {
+                params.push(self.ident()?.0);
+                if self.eat(")") {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+        let body = self.block()?;
+        Ok(Function {
+            name,
+            params,
+            body,
+            span,
+        })
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, CompileError> {
+        self.expect("{")?;
+        let mut stmts = vec![];
+        while !self.eat("}") {
+            stmts.push(self.statement()?);
+        }
+        Ok(stmts)
+    }
+
+    fn statement(&mut self) -> Result<Stmt, CompileError> {
+        if self.is_keyword("let") {
+            self.pos += 1;
+            let (name, span) = self.ident()?;
+            self.expect("=")?;
+            let value = self.expr()?;
+            self.expect(";")?;
+            Ok(Stmt::Let(name, value, span))
+        } else if self.is_keyword("if") {
+            self.pos += 1;
+            let cond = self.expr()?;
+            let then = self.block()?;
+            let otherwise = if self.is_keyword("else") {
+                self.pos += 1;
+                if self.is_keyword("if") {
+                    vec![self.statement()?]
+                } else {
+                    self.block()?
+                }
+            } else {
+                vec![]
+            };
+            Ok(Stmt::If(cond, then, otherwise))
+        } else if self.is_keyword("while") {
+            self.pos += 1;
+            let cond = self.expr()?;
+            let body = self.block()?;
+            Ok(Stmt::While(cond, body))
+        } else if self.is_keyword("return") {
+            self.pos += 1;
+            let value = if self.eat(";") {
+                None
+            } else {
+                let value = self.expr()?;
+                self.expect(";")?;
+                Some(value)
+            };
+            Ok(Stmt::Return(value))
+        } else if matches!(self.tokens.get(self.pos + 1), Some((Token::Punct("="), _))) {
+            let (name, span) = self.ident()?;
+            self.expect("=")?;
+            let value = self.expr()?;
+            self.expect(";")?;
+            Ok(Stmt::Assign(name, value, span))
+        } else {
+            let value = self.expr()?;
+            self.expect(";")?;
+            Ok(Stmt::Expr(value))
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, CompileError> {
+        self.binary(0)
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr, CompileError> {
+        const LEVELS: [&[(&str, BinOp)]; 5] = [
+            &[("||", BinOp::Or)],
+            &[("&&", BinOp::And)],
+            &[
+                ("==", BinOp::Equal),
+                ("!=", BinOp::NotEqual),
+                ("<=", BinOp::LessEq),
+                (">=", BinOp::GreaterEq),
+                ("<", BinOp::Less),
+                (">", BinOp::Greater),
+            ],
+            &[("+", BinOp::Add), ("-", BinOp::Sub)],
+            &[("*", BinOp::Mul)],
+        ];
+
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+
+        let mut lhs = self.binary(level + 1)?;
+        'more: loop {
+            for (punct, op) in LEVELS[level] {
+                if self.eat(punct) {
+                    let rhs = self.binary(level + 1)?;
+                    lhs = Expr::Binary(*op, Box::new(lhs), Box::new(rhs));
+                    continue 'more;
+                }
+            }
+            return Ok(lhs);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, CompileError> {
+        if self.eat("-") {
+            Ok(Expr::Neg(Box::new(self.unary()?)))
+        } else if self.eat("!") {
+            Ok(Expr::Not(Box::new(self.unary()?)))
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, CompileError> {
+        let span = self.span();
+        match self.peek().clone() {
+            Token::Number(n) => {
+                self.pos += 1;
+                Ok(Expr::Number(n))
+            }
+            Token::Punct("(") => {
+                self.pos += 1;
+                let e = self.expr()?;
+                self.expect(")")?;
+                Ok(e)
+            }
+            Token::Ident(_) => {
+                let (name, span) = self.ident()?;
+                if !self.eat("(") {
+                    return Ok(Expr::Var(name, span));
+                }
+                let mut args = vec![];
+                if !self.eat(")") {
+                    loop {
+                        args.push(self.expr()?);
+                        if self.eat(")") {
+                            break;
+                        }
+                        self.expect(",")?;
+                    }
+                }
+                Ok(Expr::Call(name, args, span))
+            }
+            t => span.error(format!("expected an expression, found {}", describe(&t))),
+        }
+    }
+}
+
+fn is_keyword(s: &str) -> bool {
+    matches!(s, "fn" | "let" | "if" | "else" | "while" | "return")
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Number(n) => format!("`{}`", n),
+        Token::Ident(s) => format!("`{}`", s),
+        Token::Punct(p) => format!("`{}`", p),
+        Token::Eof => String::from("end of input"),
+    }
+}
+
+type Label = usize;
+
+/// An instruction argument before layout is final.
+#[derive(Clone, Copy)]
+enum Arg {
+    Imm(Intcode),
+    Rel(Intcode),
+    /// Immediate address of a label.
+    Label(Label),
+    /// `[rb+F+k]`, where F is the current frame size.
+    FrameRel(Intcode),
+    /// `#(sign*F)`, for moving the relative base over the frame.
+    FrameSize(Intcode),
+}
+
+/// A word of output before layout is final.
+#[derive(Clone, Copy)]
+enum Word {
+    Lit(Intcode),
+    Label(Label),
+    Frame { sign: Intcode, offset: Intcode },
+}
+
+struct FunctionInfo {
+    label: Label,
+    arity: usize,
+}
+
+struct Codegen<'a> {
+    functions: &'a HashMap<String, FunctionInfo>,
+    words: Vec<Word>,
+    labels: Vec<Option<usize>>,
+    // per-function state
+    slots: HashMap<String, Intcode>,
+    declared: Vec<String>,
+    temp_base: Intcode,
+    temps: Intcode,
+    max_temps: Intcode,
+}
+
+impl Codegen<'_> {
+    fn new_label(&mut self) -> Label {
+        self.labels.push(None);
+        self.labels.len() - 1
+    }
+
+    fn place(&mut self, label: Label) {
+        self.labels[label] = Some(self.words.len());
+    }
+
+    fn emit(&mut self, opcode: Intcode, args: &[Arg]) {
+        let mut word = opcode;
+        for (argno, arg) in args.iter().enumerate() {
+            let mode = match arg {
+                Arg::Rel(_) | Arg::FrameRel(_) => PARAM_TYPE_RELATIVE,
+                _ => PARAM_TYPE_IMMEDIATE,
+            };
+            word += mode * OP_PARAMETER_BASE.pow(argno as u32 + OP_PARAMETER_BASE_POS - 1);
+        }
+
+        self.words.push(Word::Lit(word));
+        for arg in args {
+            self.words.push(match *arg {
+                Arg::Imm(n) | Arg::Rel(n) => Word::Lit(n),
+                Arg::Label(l) => Word::Label(l),
+                Arg::FrameRel(k) => Word::Frame { sign: 1, offset: k },
+                Arg::FrameSize(sign) => Word::Frame { sign, offset: 0 },
+            });
+        }
+    }
+
+    fn jump(&mut self, label: Label) {
+        self.emit(OP_JUMP_IF_TRUE, &[Arg::Imm(1), Arg::Label(label)]);
+    }
+
+    fn copy(&mut self, from: Arg, to: Arg) {
+        self.emit(OP_ADD, &[from, Arg::Imm(0), to]);
+    }
+
+    fn alloc_temp(&mut self) -> Intcode {
+        let slot = self.temp_base + self.temps;
+        self.temps += 1;
+        self.max_temps = self.max_temps.max(self.temps);
+        slot
+    }
+
+    fn free_temp(&mut self) {
+        self.temps -= 1;
+    }
+
+    fn function(&mut self, f: &Function) -> Result<(), CompileError> {
+        let start = self.words.len();
+        self.place(self.functions[&f.name].label);
+
+        self.slots.clear();
+        self.declared.clear();
+        for (i, param) in f.params.iter().enumerate() {
+            if self.slots.insert(param.clone(), 1 + i as Intcode).is_some() {
+                return f.span.error(format!("duplicate parameter `{}`", param));
+            }
+            self.declared.push(param.clone());
+        }
+
+        let mut next = 1 + f.params.len().max(1) as Intcode;
+        let mut locals = vec![];
+        collect_locals(&f.body, &mut locals);
+        for (name, span) in locals {
+            if self.slots.insert(name.clone(), next).is_some() {
+                return span.error(format!("`{}` is already declared", name));
+            }
+            next += 1;
+        }
+        self.temp_base = next;
+        self.temps = 0;
+        self.max_temps = 0;
+
+        self.block(&f.body)?;
+        self.ret(None)?;
+
+        let frame = self.temp_base + self.max_temps;
+        for word in &mut self.words[start..] {
+            if let Word::Frame { sign, offset } = *word {
+                *word = Word::Lit(sign * frame + offset);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn block(&mut self, stmts: &[Stmt]) -> Result<(), CompileError> {
+        for stmt in stmts {
+            self.statement(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, stmt: &Stmt) -> Result<(), CompileError> {
+        match stmt {
+            Stmt::Let(name, value, _) => {
+                let slot = self.slots[name];
+                self.expr(value, slot)?;
+                self.declared.push(name.clone());
+            }
+            Stmt::Assign(name, value, span) => {
+                let slot = self.var(name, *span)?;
+                self.expr(value, slot)?;
+            }
+            Stmt::If(cond, then, otherwise) => {
+                let else_label = self.new_label();
+                let end_label = self.new_label();
+                let cond = self.operand(cond)?;
+                self.emit(OP_JUMP_IF_FALSE, &[cond.0, Arg::Label(else_label)]);
+                self.release(cond);
+                self.block(then)?;
+                self.jump(end_label);
+                self.place(else_label);
+                self.block(otherwise)?;
+                self.place(end_label);
+            }
+            Stmt::While(cond, body) => {
+                let top_label = self.new_label();
+                let end_label = self.new_label();
+                self.place(top_label);
+                let cond = self.operand(cond)?;
+                self.emit(OP_JUMP_IF_FALSE, &[cond.0, Arg::Label(end_label)]);
+                self.release(cond);
+                self.block(body)?;
+                self.jump(top_label);
+                self.place(end_label);
+            }
+            Stmt::Return(value) => self.ret(value.as_ref())?,
+            Stmt::Expr(value) => {
+                let value = self.operand(value)?;
+                self.release(value);
+            }
+        }
+        Ok(())
+    }
+
+    fn ret(&mut self, value: Option<&Expr>) -> Result<(), CompileError> {
+        match value {
+            Some(value) => {
+                let value = self.operand(value)?;
+                self.copy(value.0, Arg::Rel(SLOT_RETURN_VALUE));
+                self.release(value);
+            }
+            None => self.copy(Arg::Imm(0), Arg::Rel(SLOT_RETURN_VALUE)),
+        }
+        self.emit(OP_JUMP_IF_TRUE, &[Arg::Imm(1), Arg::Rel(SLOT_RETURN_ADDR)]);
+        Ok(())
+    }
+
+    fn var(&self, name: &str, span: Span) -> Result<Intcode, CompileError> {
+        match self.slots.get(name) {
+            Some(&slot) if self.declared.iter().any(|d| d == name) => Ok(slot),
+            _ => span.error(format!("`{}` is not declared", name)),
+        }
+    }
+
+    /// Returns an argument holding the value of `e`, and whether a
+    /// temporary was used for it that must be released afterwards.
+    fn operand(&mut self, e: &Expr) -> Result<(Arg, bool), CompileError> {
+        match e {
+            Expr::Number(n) => Ok((Arg::Imm(*n), false)),
+            Expr::Var(name, span) => Ok((Arg::Rel(self.var(name, *span)?), false)),
+            _ => {
+                let t = self.alloc_temp();
+                self.expr(e, t)?;
+                Ok((Arg::Rel(t), true))
+            }
+        }
+    }
+
+    fn release(&mut self, operand: (Arg, bool)) {
+        if operand.1 {
+            self.free_temp();
+        }
+    }
+
+    /// Evaluates `e` into the frame slot `dst`. Operands are always
+    /// fully read before `dst` is written, so `dst` may appear in `e`.
+    fn expr(&mut self, e: &Expr, dst: Intcode) -> Result<(), CompileError> {
+        let out = Arg::Rel(dst);
+        match e {
+            Expr::Number(_) | Expr::Var(..) => {
+                let value = self.operand(e)?;
+                self.copy(value.0, out);
+            }
+            Expr::Neg(a) => {
+                let a = self.operand(a)?;
+                self.emit(OP_MUL, &[a.0, Arg::Imm(-1), out]);
+                self.release(a);
+            }
+            Expr::Not(a) => {
+                let a = self.operand(a)?;
+                self.emit(OP_EQUALS, &[a.0, Arg::Imm(0), out]);
+                self.release(a);
+            }
+            Expr::Binary(op @ (BinOp::And | BinOp::Or), a, b) => {
+                let t = self.alloc_temp();
+                let done = self.new_label();
+                self.expr(a, t)?;
+                let skip = if *op == BinOp::And {
+                    OP_JUMP_IF_FALSE
+                } else {
+                    OP_JUMP_IF_TRUE
+                };
+                self.emit(skip, &[Arg::Rel(t), Arg::Label(done)]);
+                self.expr(b, t)?;
+                self.place(done);
+                self.emit(OP_EQUALS, &[Arg::Rel(t), Arg::Imm(0), Arg::Rel(t)]);
+                self.emit(OP_EQUALS, &[Arg::Rel(t), Arg::Imm(0), out]);
+                self.free_temp();
+            }
+            Expr::Binary(op, a, b) => {
+                let a = self.operand(a)?;
+                let b = self.operand(b)?;
+                let (x, y) = (a.0, b.0);
+                match op {
+                    BinOp::Add => self.emit(OP_ADD, &[x, y, out]),
+                    BinOp::Sub => {
+                        let t = self.alloc_temp();
+                        self.emit(OP_MUL, &[y, Arg::Imm(-1), Arg::Rel(t)]);
+                        self.emit(OP_ADD, &[x, Arg::Rel(t), out]);
+                        self.free_temp();
+                    }
+                    BinOp::Mul => self.emit(OP_MUL, &[x, y, out]),
+                    BinOp::Less => self.emit(OP_LESS_THAN, &[x, y, out]),
+                    BinOp::Greater => self.emit(OP_LESS_THAN, &[y, x, out]),
+                    BinOp::LessEq => {
+                        self.emit(OP_LESS_THAN, &[y, x, out]);
+                        self.emit(OP_EQUALS, &[out, Arg::Imm(0), out]);
+                    }
+                    BinOp::GreaterEq => {
+                        self.emit(OP_LESS_THAN, &[x, y, out]);
+                        self.emit(OP_EQUALS, &[out, Arg::Imm(0), out]);
+                    }
+                    BinOp::Equal => self.emit(OP_EQUALS, &[x, y, out]),
+                    BinOp::NotEqual => {
+                        self.emit(OP_EQUALS, &[x, y, out]);
+                        self.emit(OP_EQUALS, &[out, Arg::Imm(0), out]);
+                    }
+                    BinOp::And | BinOp::Or => unreachable!(),
+                }
+                self.release(b);
+                self.release(a);
+            }
+            Expr::Call(name, args, span) => self.call(name, args, *span, dst)?,
+        }
+        Ok(())
+    }
+
+    fn call(
+        &mut self,
+        name: &str,
+        args: &[Expr],
+        span: Span,
+        dst: Intcode,
+    ) -> Result<(), CompileError> {
+        match (name, args) {
+            ("input", []) => {
+                self.emit(OP_STORE_INPUT, &[Arg::Rel(dst)]);
+                return Ok(());
+            }
+            ("output", [value]) => {
+                let value = self.operand(value)?;
+                self.emit(OP_WRITE_OUTPUT, &[value.0]);
+                self.copy(value.0, Arg::Rel(dst));
+                self.release(value);
+                return Ok(());
+            }
+            ("input" | "output", _) => {
+                return span.error(format!("wrong number of arguments to `{}`", name));
+            }
+            _ => (),
+        }
+
+        let Some(f) = self.functions.get(name) else {
+            return span.error(format!("no function named `{}`", name));
+        };
+        if f.arity != args.len() {
+            return span.error(format!(
+                "`{}` takes {} arguments but {} were given",
+                name,
+                f.arity,
+                args.len()
+            ));
+        }
+        let target = f.label;
+
+        // Arguments go through temporaries first, since a call nested
+        // in a later argument would reuse the callee frame.
+        let temps: Vec<Intcode> = args.iter().map(|_| self.alloc_temp()).collect();
+        for (arg, &t) in args.iter().zip(&temps) {
+            self.expr(arg, t)?;
+        }
+        for (i, &t) in temps.iter().enumerate() {
+            self.copy(Arg::Rel(t), Arg::FrameRel(1 + i as Intcode));
+        }
+        for _ in &temps {
+            self.free_temp();
+        }
+
+        let back = self.new_label();
+        self.copy(Arg::Label(back), Arg::FrameRel(SLOT_RETURN_ADDR));
+        self.emit(OP_ADJUST_RELATIVE_BASE, &[Arg::FrameSize(1)]);
+        self.jump(target);
+        self.place(back);
+        self.emit(OP_ADJUST_RELATIVE_BASE, &[Arg::FrameSize(-1)]);
+        self.copy(Arg::FrameRel(SLOT_RETURN_VALUE), Arg::Rel(dst));
+        Ok(())
+    }
+}
+
+fn collect_locals(stmts: &[Stmt], locals: &mut Vec<(String, Span)>) {
+    for stmt in stmts {
+        match stmt {
+            Stmt::Let(name, _, span) => locals.push((name.clone(), *span)),
+            Stmt::If(_, then, otherwise) => {
+                collect_locals(then, locals);
+                collect_locals(otherwise, locals);
+            }
+            Stmt::While(_, body) => collect_locals(body, locals),
+            _ => (),
+        }
+    }
+}
+
+/// Compiles a program to Intcode that runs on `Computer` as is.
+pub fn compile(source: &str) -> Result<Vec<Intcode>, CompileError> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let program = parser.program()?;
+
+    let mut functions = HashMap::new();
+    for (i, f) in program.iter().enumerate() {
+        let info = FunctionInfo {
+            label: i,
+            arity: f.params.len(),
+        };
+        if matches!(f.name.as_str(), "input" | "output")
+            || functions.insert(f.name.clone(), info).is_some()
+        {
+            return f.span.error(format!("`{}` is already defined", f.name));
+        }
+    }
+
+    let Some(main) = program.iter().find(|f| f.name == "main") else {
+        return Span { line: 1, col: 1 }.error("no `main` function");
+    };
+    if !main.params.is_empty() {
+        return main.span.error("`main` takes no parameters");
+    }
+
+    let mut codegen = Codegen {
+        functions: &functions,
+        words: vec![],
+        labels: vec![None; program.len()],
+        slots: HashMap::new(),
+        declared: vec![],
+        temp_base: 0,
+        temps: 0,
+        max_temps: 0,
+    };
+
+    // Set up the stack just past the code, call main, halt.
+    let stack = codegen.new_label();
+    let halt = codegen.new_label();
+    codegen.emit(OP_ADJUST_RELATIVE_BASE, &[Arg::Label(stack)]);
+    codegen.copy(Arg::Label(halt), Arg::Rel(SLOT_RETURN_ADDR));
+    codegen.jump(functions["main"].label);
+    codegen.place(halt);
+    codegen.emit(OP_HALT, &[]);
+
+    for f in &program {
+        codegen.function(f)?;
+    }
+    codegen.place(stack);
+
+    let labels = codegen.labels;
+    Ok(codegen
+        .words
+        .into_iter()
+        .map(|word| match word {
+            Word::Lit(n) => n,
+            Word::Label(l) => labels[l].expect("all labels are placed") as Intcode,
+            Word::Frame { .. } => unreachable!("frame sizes are patched per function"),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::computer::Computer;
+    use crate::optimizer::{verify, Optimizer};
+
+    fn run(source: &str, inputs: &[Intcode]) -> Vec<Intcode> {
+        let program = compile(source).unwrap_or_else(|e| panic!("{}", e));
+        let mut computer = Computer::new(program);
+        computer.buffer_inputs(inputs.iter().copied());
+        computer.start();
+        assert!(computer.is_halted());
+        computer.consume_output_buffer().collect()
+    }
+
+    const FIB: &str = "
+        // print the first n fibonacci numbers
+        fn main() {
+            let n = input();
+            let i = 0;
+            while i < n {
+                output(fib(i));
+                i = i + 1;
+            }
+        }
+
+        fn fib(n) {
+            if n < 2 {
+                return n;
+            }
+            return fib(n - 1) + fib(n - 2);
+        }
+    ";
+
+    #[test]
+    fn recursion() {
+        assert_eq!(vec![0, 1, 1, 2, 3, 5, 8, 13, 21, 34], run(FIB, &[10]));
+    }
+
+    #[test]
+    fn operators() {
+        let source = "
+            fn main() {
+                let a = input();
+                let b = input();
+                output(a - b);
+                output(-a * b);
+                output((a < b) + 2 * (a <= b) + 4 * (a > b) + 8 * (a >= b));
+                output((a == b) + 2 * (a != b) + 4 * !a);
+                output(a && b || 0);
+                output(0 || b);
+            }
+        ";
+        assert_eq!(vec![-4, -21, 3, 2, 1, 1], run(source, &[3, 7]));
+        assert_eq!(vec![0, 0, 10, 5, 0, 0], run(source, &[0, 0]));
+    }
+
+    #[test]
+    fn calls_and_control_flow() {
+        let source = "
+            fn main() {
+                let x = input();
+                if x > 100 {
+                    output(1);
+                } else if x > 10 {
+                    output(2);
+                } else {
+                    output(3);
+                }
+                output(sum(x, max(x, 5), 3 * 2));
+                noop();
+            }
+
+            fn max(a, b) {
+                if a > b { return a; }
+                return b;
+            }
+
+            fn sum(a, b, c) {
+                return a + b + c;
+            }
+
+            fn noop() {
+                return;
+            }
+        ";
+        assert_eq!(vec![2, 66], run(source, &[30]));
+        assert_eq!(vec![3, 10], run(source, &[-1]));
+    }
+
+    #[test]
+    fn errors() {
+        let err = |source| compile(source).unwrap_err().to_string();
+        assert_eq!("1:1: no `main` function", err(""));
+        assert_eq!("1:21: `y` is not declared", err("fn main() { let x = y; }"));
+        assert_eq!(
+            "1:13: `f` takes 1 arguments but 0 were given",
+            err("fn main() { f(); } fn f(a) { }")
+        );
+        assert_eq!("1:15: expected `;`, found `}`", err("fn main() { 1 }"));
+        assert_eq!("1:13: unexpected character `$`", err("fn main() { $ }"));
+    }
+
+    #[test]
+    fn optimizer_workload() {
+        let program = compile(FIB).unwrap();
+        let optimized = Optimizer::new().relative_is_stack(true).optimize(&program);
+        assert_eq!(Ok(()), verify(&program, &optimized.program, &[12]));
+    }
+}
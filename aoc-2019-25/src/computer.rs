@@ -3,29 +3,211 @@
 // mod computer;
 // use computer::{Computer, Intcode};
 
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
 use std::mem::swap;
+use std::sync::{Arc, Mutex};
 
 pub type Intcode = i64;
 
-const OP_ADD: Intcode = 1;
-const OP_MUL: Intcode = 2;
-const OP_STORE_INPUT: Intcode = 3;
-const OP_WRITE_OUTPUT: Intcode = 4;
-const OP_JUMP_IF_TRUE: Intcode = 5;
-const OP_JUMP_IF_FALSE: Intcode = 6;
-const OP_LESS_THAN: Intcode = 7;
-const OP_EQUALS: Intcode = 8;
-const OP_ADJUST_RELATIVE_BASE: Intcode = 9;
-const OP_HALT: Intcode = 99;
+pub(crate) const OP_ADD: Intcode = 1;
+pub(crate) const OP_MUL: Intcode = 2;
+pub(crate) const OP_STORE_INPUT: Intcode = 3;
+pub(crate) const OP_WRITE_OUTPUT: Intcode = 4;
+pub(crate) const OP_JUMP_IF_TRUE: Intcode = 5;
+pub(crate) const OP_JUMP_IF_FALSE: Intcode = 6;
+pub(crate) const OP_LESS_THAN: Intcode = 7;
+pub(crate) const OP_EQUALS: Intcode = 8;
+pub(crate) const OP_ADJUST_RELATIVE_BASE: Intcode = 9;
+pub(crate) const OP_HALT: Intcode = 99;
 
-const OP_PARAMETER_BASE: Intcode = 10;
-const OP_PARAMETER_BASE_POS: u32 = 3;
+const BUILTIN_OPS: [Intcode; 10] = [
+    OP_ADD,
+    OP_MUL,
+    OP_STORE_INPUT,
+    OP_WRITE_OUTPUT,
+    OP_JUMP_IF_TRUE,
+    OP_JUMP_IF_FALSE,
+    OP_LESS_THAN,
+    OP_EQUALS,
+    OP_ADJUST_RELATIVE_BASE,
+    OP_HALT,
+];
 
-const PARAM_TYPE_POSITION: Intcode = 0;
-const PARAM_TYPE_IMMEDIATE: Intcode = 1;
-const PARAM_TYPE_RELATIVE: Intcode = 2;
+pub(crate) const OP_PARAMETER_BASE: Intcode = 10;
+pub(crate) const OP_PARAMETER_BASE_POS: u32 = 3;
 
+pub(crate) const PARAM_TYPE_POSITION: Intcode = 0;
+pub(crate) const PARAM_TYPE_IMMEDIATE: Intcode = 1;
+pub(crate) const PARAM_TYPE_RELATIVE: Intcode = 2;
+
+/// Leading bytes of the binary program encoding. The NUL keeps it
+/// from ever being mistaken for a text program.
+const BINARY_MAGIC: &[u8] = b"\0ICB";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    InvalidValue { offset: usize, token: String },
+    EmptyValue { offset: usize },
+    InvalidUtf8 { offset: usize },
+    BadMagic,
+    Truncated { offset: usize },
+    Overflow { offset: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidValue { offset, token } => {
+                write!(f, "invalid value `{}` at byte {}", token, offset)
+            }
+            ParseError::EmptyValue { offset } => {
+                write!(f, "missing value before comma at byte {}", offset)
+            }
+            ParseError::InvalidUtf8 { offset } => write!(f, "invalid utf-8 at byte {}", offset),
+            ParseError::BadMagic => write!(f, "not a binary intcode program"),
+            ParseError::Truncated { offset } => write!(f, "truncated value at byte {}", offset),
+            ParseError::Overflow { offset } => write!(f, "value too large at byte {}", offset),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RegisterError {
+    Builtin(Intcode),
+    Duplicate(Intcode),
+    OutOfRange(Intcode),
+    /// More parameters than an instruction word has digits for modes.
+    TooManyParams(u32),
+}
+
+impl fmt::Display for RegisterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegisterError::Builtin(x) => write!(f, "opcode {} is a builtin", x),
+            RegisterError::Duplicate(x) => write!(f, "opcode {} is already registered", x),
+            RegisterError::OutOfRange(x) => write!(f, "opcode {} doesn't fit in two digits", x),
+            RegisterError::TooManyParams(n) => {
+                write!(f, "modes for {} parameters don't fit in an instruction", n)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RegisterError {}
+
+type OpHandler = Arc<Mutex<dyn FnMut(&mut OpContext) + Send>>;
+
+#[derive(Clone)]
+struct Extension {
+    param_count: u32,
+    handler: OpHandler,
+}
+
+/// Extra opcodes a `Computer` will accept on top of the builtins.
+///
+/// Handlers are shared, not copied, between registries cloned from
+/// one another, so any state they capture is shared too.
+#[derive(Clone, Default)]
+pub struct OpRegistry {
+    ops: HashMap<Intcode, Extension>,
+}
+
+impl OpRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `opcode` as taking `param_count` parameters, which are
+    /// decoded with the usual parameter modes and handed to `handler`
+    /// each time the instruction runs.
+    pub fn register<F>(
+        &mut self,
+        opcode: Intcode,
+        param_count: u32,
+        handler: F,
+    ) -> Result<&mut Self, RegisterError>
+    where
+        F: FnMut(&mut OpContext) + Send + 'static,
+    {
+        if !(1..OP_PARAMETER_BASE.pow(OP_PARAMETER_BASE_POS - 1)).contains(&opcode) {
+            return Err(RegisterError::OutOfRange(opcode));
+        }
+        if BUILTIN_OPS.contains(&opcode) {
+            return Err(RegisterError::Builtin(opcode));
+        }
+        if self.ops.contains_key(&opcode) {
+            return Err(RegisterError::Duplicate(opcode));
+        }
+        // the last parameter's mode is the highest digit an Intcode has
+        if param_count > Intcode::MAX.ilog10() + 1 - OP_PARAMETER_BASE_POS {
+            return Err(RegisterError::TooManyParams(param_count));
+        }
+
+        self.ops.insert(
+            opcode,
+            Extension {
+                param_count,
+                handler: Arc::new(Mutex::new(handler)),
+            },
+        );
+        Ok(self)
+    }
+}
+
+/// What an extension handler can see and do while its instruction
+/// runs.
+pub struct OpContext<'a> {
+    computer: &'a mut Computer,
+    params: &'a [Parameter],
+}
+
+impl OpContext<'_> {
+    /// The value of parameter `argno`, dereferenced according to its
+    /// mode.
+    pub fn arg(&self, argno: usize) -> Intcode {
+        self.computer.deref(&self.params[argno])
+    }
+
+    pub fn arg_count(&self) -> usize {
+        self.params.len()
+    }
+
+    /// Stops the machine and records `code` as its exit code.
+    pub fn halt(&mut self, code: Intcode) {
+        self.computer.exit_code = Some(code);
+        self.computer.state = ComputerState::Halted;
+    }
+
+    pub fn jump(&mut self, addr: Intcode) {
+        self.computer.ip = addr;
+    }
+
+    pub fn output(&mut self, value: Intcode) {
+        self.computer.outputs.push_back(value);
+    }
+
+    pub fn read(&self, p: Intcode) -> Intcode {
+        self.computer.read(p)
+    }
+
+    /// Writes `value` to the address parameter `argno` refers to.
+    pub fn set(&mut self, argno: usize, value: Intcode) {
+        match self.params[argno] {
+            Position(p) => self.computer.write(p, value),
+            Relative(o) => self.computer.write(o + self.computer.relative_base, value),
+            _ => panic!("Extension arg {argno} must be Position or Relative to be set"),
+        }
+    }
+
+    pub fn write(&mut self, p: Intcode, value: Intcode) {
+        self.computer.write(p, value);
+    }
+}
+
+#[derive(Clone)]
 enum Op {
     Add(Parameter, Parameter, Parameter),
     Mul(Parameter, Parameter, Parameter),
@@ -37,6 +219,7 @@ enum Op {
     Equals(Parameter, Parameter, Parameter),
     AdjustRelativeBase(Parameter),
     Halt,
+    Extension(Intcode, Vec<Parameter>),
 }
 
 #[derive(Clone, Copy)]
@@ -66,20 +249,20 @@ use Parameter::*;
 ///  ||+--- Param 0 type = 0 (PARAM_TYPE_POSTIION)
 ///  |+---- Param 1 type = 1 (PARAM_TYPE_IMMEDIATE)
 ///  +----- Param 2 type = 0 (PARAM_TYPE_POSITION)
-struct OpDecoder(Intcode);
+pub(crate) struct OpDecoder(pub(crate) Intcode);
 
 impl OpDecoder {
-    fn opcode(&self) -> Intcode {
+    pub(crate) fn opcode(&self) -> Intcode {
         self.0 % OP_PARAMETER_BASE.pow(OP_PARAMETER_BASE_POS - 1)
     }
 
-    fn param_type(&self, argno: u32) -> Intcode {
+    pub(crate) fn param_type(&self, argno: u32) -> Intcode {
         self.0 % (OP_PARAMETER_BASE.pow(argno + OP_PARAMETER_BASE_POS))
             / OP_PARAMETER_BASE.pow(argno + OP_PARAMETER_BASE_POS - 1)
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum ComputerState {
     Initial,
     Running,
@@ -87,6 +270,24 @@ enum ComputerState {
     AwaitingInput,
 }
 
+/// Why `run_for` or `run_until` handed control back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// The instruction budget ran out before anything else happened.
+    BudgetExhausted,
+    /// The last instruction wrote an output, which is waiting in the
+    /// output buffer.
+    Output,
+    AwaitingInput,
+    Halted,
+    /// The `run_until` predicate was satisfied.
+    Condition,
+    /// The next word isn't an instruction this machine knows, or has a
+    /// parameter mode it can't decode. Running it would panic.
+    Fault,
+}
+
+#[derive(Clone)]
 pub struct Computer {
     memory: Vec<Intcode>,
     ip: Intcode,
@@ -95,6 +296,8 @@ pub struct Computer {
     outputs: VecDeque<Intcode>,
     op: Option<Op>,
     relative_base: Intcode,
+    ops: OpRegistry,
+    exit_code: Option<Intcode>,
 }
 
 impl Computer {
@@ -115,10 +318,129 @@ impl Computer {
     }
 
     pub fn parse_program(prog: &str) -> Vec<Intcode> {
-        prog.trim().split(",").map(|s| s.parse().unwrap()).collect()
+        Self::try_parse_program(prog).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Parses a text program. Values may be separated by commas,
+    /// whitespace or both, a single trailing comma is allowed, and `#`
+    /// starts a comment that runs to the end of the line.
+    pub fn try_parse_program(prog: &str) -> Result<Vec<Intcode>, ParseError> {
+        let mut program = vec![];
+        let mut after_comma = false;
+        let mut chars = prog.char_indices().peekable();
+
+        while let Some(&(offset, c)) = chars.peek() {
+            match c {
+                '#' => while chars.next_if(|&(_, c)| c != '\n').is_some() {},
+                ',' => {
+                    if after_comma || program.is_empty() {
+                        return Err(ParseError::EmptyValue { offset });
+                    }
+                    after_comma = true;
+                    chars.next();
+                }
+                c if c.is_whitespace() => {
+                    chars.next();
+                }
+                _ => {
+                    let mut end = prog.len();
+                    while let Some(&(o, c)) = chars.peek() {
+                        if c == ',' || c == '#' || c.is_whitespace() {
+                            end = o;
+                            break;
+                        }
+                        chars.next();
+                    }
+
+                    let token = &prog[offset..end];
+                    let value = token.parse().map_err(|_| ParseError::InvalidValue {
+                        offset,
+                        token: token.to_owned(),
+                    })?;
+                    program.push(value);
+                    after_comma = false;
+                }
+            }
+        }
+
+        Ok(program)
+    }
+
+    /// Loads a program in either the binary or the text encoding,
+    /// deciding by the leading magic bytes.
+    pub fn load_program(bytes: &[u8]) -> Result<Vec<Intcode>, ParseError> {
+        if bytes.starts_with(BINARY_MAGIC) {
+            return Self::decode_program_binary(bytes);
+        }
+
+        let text = std::str::from_utf8(bytes).map_err(|e| ParseError::InvalidUtf8 {
+            offset: e.valid_up_to(),
+        })?;
+        Self::try_parse_program(text)
+    }
+
+    /// Encodes a program compactly: the magic bytes followed by each
+    /// value as a zigzag LEB128 varint, so small values of either sign
+    /// take a single byte.
+    pub fn encode_program_binary(program: &[Intcode]) -> Vec<u8> {
+        let mut bytes = BINARY_MAGIC.to_vec();
+
+        for &value in program {
+            let mut zigzag = ((value << 1) ^ (value >> (Intcode::BITS - 1))) as u64;
+            loop {
+                let byte = (zigzag & 0x7f) as u8;
+                zigzag >>= 7;
+                if zigzag == 0 {
+                    bytes.push(byte);
+                    break;
+                }
+                bytes.push(byte | 0x80);
+            }
+        }
+
+        bytes
+    }
+
+    pub fn decode_program_binary(bytes: &[u8]) -> Result<Vec<Intcode>, ParseError> {
+        if !bytes.starts_with(BINARY_MAGIC) {
+            return Err(ParseError::BadMagic);
+        }
+
+        let mut program = vec![];
+        let mut offset = BINARY_MAGIC.len();
+
+        while offset < bytes.len() {
+            let start = offset;
+            let mut zigzag: u64 = 0;
+            let mut shift = 0;
+            loop {
+                let byte = *bytes
+                    .get(offset)
+                    .ok_or(ParseError::Truncated { offset: start })?;
+                offset += 1;
+
+                let bits = u64::from(byte & 0x7f);
+                if shift >= u64::BITS || (bits << shift) >> shift != bits {
+                    return Err(ParseError::Overflow { offset: start });
+                }
+                zigzag |= bits << shift;
+                shift += 7;
+
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+            program.push((zigzag >> 1) as Intcode ^ -((zigzag & 1) as Intcode));
+        }
+
+        Ok(program)
     }
 
     pub fn new(memory: Vec<Intcode>) -> Self {
+        Self::with_ops(memory, OpRegistry::new())
+    }
+
+    pub fn with_ops(memory: Vec<Intcode>, ops: OpRegistry) -> Self {
         Computer {
             memory,
             inputs: VecDeque::new(),
@@ -127,6 +449,8 @@ impl Computer {
             outputs: VecDeque::new(),
             op: None,
             relative_base: 0,
+            ops,
+            exit_code: None,
         }
     }
 
@@ -196,9 +520,29 @@ impl Computer {
                 self.adjust_relative_base(*pa);
             }
             Op::Halt => self.state = ComputerState::Halted,
+            Op::Extension(opcode, params) => {
+                let opcode = *opcode;
+                let params = params.clone();
+                self.extension(opcode, &params);
+            }
         }
     }
 
+    /// The code an extension halted the machine with, if it was one
+    /// that halted it.
+    pub fn exit_code(&self) -> Option<Intcode> {
+        self.exit_code
+    }
+
+    fn extension(&mut self, opcode: Intcode, params: &[Parameter]) {
+        let handler = Arc::clone(&self.ops.ops[&opcode].handler);
+        let mut handler = handler.lock().unwrap();
+        handler(&mut OpContext {
+            computer: self,
+            params,
+        });
+    }
+
     pub fn consume_output(&mut self) -> Option<Intcode> {
         self.outputs.pop_front()
     }
@@ -217,6 +561,10 @@ impl Computer {
         ComputerState::Halted == self.state
     }
 
+    pub fn memory(&self) -> &[Intcode] {
+        &self.memory
+    }
+
     fn jump_if_false(&mut self, pa: Parameter, pb: Parameter) {
         let cond = self.deref(&pa);
         if cond == 0 {
@@ -243,6 +591,27 @@ impl Computer {
         }
     }
 
+    /// Whether the instruction at `ip` can be read without panicking.
+    fn can_decode(&self) -> bool {
+        if self.ip < 0 {
+            return false;
+        }
+        let op = OpDecoder(self.read(self.ip));
+        let param_count = match op.opcode() {
+            OP_HALT => 0,
+            OP_STORE_INPUT | OP_WRITE_OUTPUT | OP_ADJUST_RELATIVE_BASE => 1,
+            OP_JUMP_IF_TRUE | OP_JUMP_IF_FALSE => 2,
+            OP_ADD | OP_MUL | OP_LESS_THAN | OP_EQUALS => 3,
+            x => match self.ops.ops.get(&x) {
+                Some(ext) => ext.param_count,
+                None => return false,
+            },
+        };
+        (0..param_count).all(|argno| {
+            (PARAM_TYPE_POSITION..=PARAM_TYPE_RELATIVE).contains(&op.param_type(argno))
+        })
+    }
+
     fn read_op_and_advance(&mut self) -> OpDecoder {
         OpDecoder(self.read_word_and_advance())
     }
@@ -301,10 +670,33 @@ impl Computer {
             OP_EQUALS => op_read_params!(Equals, 3),
             OP_ADJUST_RELATIVE_BASE => op_read_params!(AdjustRelativeBase, 1),
             OP_HALT => Op::Halt,
-            x => panic!("Unknown opcode {x}"),
+            x => match self.ops.ops.get(&x) {
+                Some(ext) => {
+                    let params = (0..ext.param_count)
+                        .map(|argno| self.read_param_and_advance(op.param_type(argno)))
+                        .collect();
+                    Op::Extension(x, params)
+                }
+                None => panic!("Unknown opcode {x}"),
+            },
         });
     }
 
+    /// Registers an extra opcode on this machine. See
+    /// `OpRegistry::register`.
+    pub fn register_op<F>(
+        &mut self,
+        opcode: Intcode,
+        param_count: u32,
+        handler: F,
+    ) -> Result<(), RegisterError>
+    where
+        F: FnMut(&mut OpContext) + Send + 'static,
+    {
+        self.ops.register(opcode, param_count, handler)?;
+        Ok(())
+    }
+
     pub fn result_addr0(&self) -> Intcode {
         assert_eq!(ComputerState::Halted, self.state);
         self.read(0)
@@ -325,6 +717,48 @@ impl Computer {
         self.compute();
     }
 
+    fn run(&mut self, mut budget: usize, mut until: impl FnMut(&Computer) -> bool) -> StopReason {
+        loop {
+            match self.state {
+                ComputerState::Halted => return StopReason::Halted,
+                ComputerState::AwaitingInput if self.inputs.is_empty() => {
+                    return StopReason::AwaitingInput
+                }
+                _ => (),
+            }
+            if self.state != ComputerState::AwaitingInput && !self.can_decode() {
+                return StopReason::Fault;
+            }
+            if until(self) {
+                return StopReason::Condition;
+            }
+            if budget == 0 {
+                return StopReason::BudgetExhausted;
+            }
+            budget -= 1;
+
+            let outputs = self.outputs.len();
+            self.step();
+            if self.outputs.len() > outputs {
+                return StopReason::Output;
+            }
+        }
+    }
+
+    /// Runs at most `budget` instructions, stopping early if the
+    /// machine produces output, needs input or halts. It can be called
+    /// again to carry on from wherever it stopped.
+    pub fn run_for(&mut self, budget: usize) -> StopReason {
+        self.run(budget, |_| false)
+    }
+
+    /// Like `run_for` without a budget, but also stops as soon as
+    /// `until` holds. The predicate is checked before each
+    /// instruction.
+    pub fn run_until(&mut self, until: impl FnMut(&Computer) -> bool) -> StopReason {
+        self.run(usize::MAX, until)
+    }
+
     pub fn start(&mut self) {
         assert_eq!(ComputerState::Initial, self.state);
         assert_eq!(0, self.ip);
@@ -337,10 +771,25 @@ impl Computer {
         match &self.state {
             ComputerState::Initial => self.start(),
             ComputerState::AwaitingInput => self.resume(),
+            ComputerState::Running => self.compute(),
             s => panic!("unexpected state {:?}", s),
         }
     }
 
+    /// Executes a single instruction, including one left waiting on
+    /// input.
+    fn step(&mut self) {
+        match self.state {
+            ComputerState::Initial | ComputerState::Running => {
+                self.state = ComputerState::Running;
+                self.read_next_instruction();
+            }
+            ComputerState::AwaitingInput => self.state = ComputerState::Running,
+            ComputerState::Halted => panic!("can't step a halted computer"),
+        }
+        self.execute();
+    }
+
     fn store_input(&mut self, pa: Parameter) {
         if let Some(input) = self.read_input() {
             match pa {
@@ -368,3 +817,223 @@ impl Computer {
         self.outputs.push_back(value);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_program_formats() {
+        assert_eq!(
+            vec![1, 0, 0, 0, 99],
+            Computer::parse_program("1,0,0,0,99\n")
+        );
+        assert_eq!(
+            vec![1, 0, 0, 0, 99],
+            Computer::parse_program("1,0,\n0,0,\n99,\n")
+        );
+        assert_eq!(
+            vec![1101, 2, -3, 4, 99],
+            Computer::parse_program("# add\n1101 2 -3 4 # 2 + -3\n  99\n")
+        );
+    }
+
+    #[test]
+    fn parse_program_errors() {
+        assert_eq!(
+            Err(ParseError::InvalidValue {
+                offset: 5,
+                token: String::from("x9"),
+            }),
+            Computer::try_parse_program("1,0,\nx9,99")
+        );
+        assert_eq!(
+            Err(ParseError::EmptyValue { offset: 2 }),
+            Computer::try_parse_program("1,,2")
+        );
+        assert_eq!(
+            Err(ParseError::EmptyValue { offset: 0 }),
+            Computer::try_parse_program(",1")
+        );
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let program = vec![0, 1, -1, 63, -64, 64, 109, Intcode::MAX, Intcode::MIN, 99];
+        let bytes = Computer::encode_program_binary(&program);
+        assert_eq!(Ok(program.clone()), Computer::decode_program_binary(&bytes));
+        assert_eq!(Ok(program), Computer::load_program(&bytes));
+
+        assert_eq!(
+            Err(ParseError::Truncated { offset: 4 }),
+            Computer::decode_program_binary(b"\0ICB\x80")
+        );
+        assert_eq!(
+            Err(ParseError::BadMagic),
+            Computer::decode_program_binary(b"1,2")
+        );
+        assert_eq!(Ok(vec![1, 2]), Computer::load_program(b"1,2\n"));
+    }
+
+    #[test]
+    fn extension_ops() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let mut ops = OpRegistry::new();
+        let debug_log = Arc::clone(&log);
+        ops.register(10, 1, move |ctx| debug_log.lock().unwrap().push(ctx.arg(0)))
+            .unwrap()
+            .register(20, 2, |ctx| {
+                let squared = ctx.arg(0) * ctx.arg(0);
+                ctx.set(1, squared);
+            })
+            .unwrap()
+            .register(30, 1, |ctx| ctx.halt(ctx.arg(0)))
+            .unwrap();
+
+        let program = vec![
+            110, 7, // dbg #7
+            120, 5, 20, // square #5 -> [20]
+            4, 20, // out [20]
+            130, 3, // halt #3
+            99,
+        ];
+        let mut computer = Computer::with_ops(program.clone(), ops.clone());
+        computer.start();
+        assert!(computer.is_halted());
+        assert_eq!(Some(3), computer.exit_code());
+        assert_eq!(
+            vec![25],
+            computer.consume_output_buffer().collect::<Vec<_>>()
+        );
+
+        // machines sharing a registry share the handlers' state
+        let mut computer = Computer::with_ops(program, ops);
+        computer.start();
+        assert_eq!(vec![7, 7], *log.lock().unwrap());
+
+        let mut computer = Computer::new(vec![99]);
+        computer.start();
+        assert_eq!(None, computer.exit_code());
+    }
+
+    #[test]
+    fn extension_register_errors() {
+        let mut ops = OpRegistry::new();
+        assert!(ops.register(42, 0, |_| ()).is_ok());
+        assert_eq!(
+            Some(RegisterError::Duplicate(42)),
+            ops.register(42, 0, |_| ()).err()
+        );
+        assert_eq!(
+            Some(RegisterError::Builtin(OP_HALT)),
+            ops.register(OP_HALT, 0, |_| ()).err()
+        );
+        assert_eq!(
+            Some(RegisterError::OutOfRange(100)),
+            ops.register(100, 0, |_| ()).err()
+        );
+        assert_eq!(
+            Some(RegisterError::OutOfRange(0)),
+            ops.register(0, 0, |_| ()).err()
+        );
+        assert_eq!(
+            Some(RegisterError::TooManyParams(17)),
+            ops.register(43, 17, |_| ()).err()
+        );
+
+        // as many parameters as there are digits for, all immediate
+        ops.register(43, 16, |ctx| {
+            let sum = (0..ctx.arg_count()).map(|i| ctx.arg(i)).sum();
+            ctx.halt(sum)
+        })
+        .unwrap();
+        let mut program = vec![43 + 1_111_111_111_111_111 * 100];
+        program.extend(1..=16);
+        let mut computer = Computer::with_ops(program, ops);
+        computer.start();
+        assert_eq!(Some(136), computer.exit_code());
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown opcode 43")]
+    fn extension_unknown_opcode() {
+        let mut computer = Computer::new(vec![43, 99]);
+        computer.register_op(42, 0, |_| ()).unwrap();
+        computer.start();
+    }
+
+    fn counter() -> Computer {
+        let mut program = vec![
+            1001, 20, 1, 20, // add [20], #1 -> [20]
+            4, 20, // out [20]
+            1007, 20, 5, 21, // lt [20], #5 -> [21]
+            1005, 21, 0, // jt [21], #0
+            3, 22, // in -> [22]
+            99,
+        ];
+        program.resize(23, 0);
+        Computer::new(program)
+    }
+
+    #[test]
+    fn run_for_preempts() {
+        let mut computer = counter();
+        assert_eq!(StopReason::BudgetExhausted, computer.run_for(1));
+        assert_eq!(1, computer.memory()[20]);
+        assert_eq!(StopReason::Output, computer.run_for(10));
+        assert_eq!(Some(1), computer.consume_output());
+
+        let mut outputs = vec![];
+        loop {
+            match computer.run_for(3) {
+                StopReason::Output => outputs.extend(computer.consume_output_buffer()),
+                StopReason::BudgetExhausted => (),
+                reason => {
+                    assert_eq!(StopReason::AwaitingInput, reason);
+                    break;
+                }
+            }
+        }
+        assert_eq!(vec![2, 3, 4, 5], outputs);
+
+        assert_eq!(StopReason::AwaitingInput, computer.run_for(100));
+        computer.buffer_input(7);
+        assert_eq!(StopReason::Halted, computer.run_for(100));
+        assert_eq!(7, computer.memory()[22]);
+        assert_eq!(StopReason::Halted, computer.run_for(100));
+    }
+
+    #[test]
+    fn run_until_condition() {
+        let mut computer = counter();
+        let mut reason = StopReason::Output;
+        while reason == StopReason::Output {
+            reason = computer.run_until(|c| c.memory()[20] == 3);
+        }
+        assert_eq!(StopReason::Condition, reason);
+        assert_eq!(
+            vec![1, 2],
+            computer.consume_output_buffer().collect::<Vec<_>>()
+        );
+
+        // a preempted machine carries on with the usual entry points
+        computer.start_or_resume();
+        assert!(computer.is_awaiting_input());
+        assert_eq!(
+            vec![3, 4, 5],
+            computer.consume_output_buffer().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn run_for_faults() {
+        // out #1; then an unknown opcode, then a bad parameter mode
+        let mut computer = Computer::new(vec![104, 1, 42, 0, 0]);
+        assert_eq!(StopReason::Output, computer.run_for(100));
+        assert_eq!(StopReason::Fault, computer.run_for(100));
+        assert_eq!(StopReason::Fault, computer.run_for(100));
+
+        let mut computer = Computer::new(vec![304, 0, 99]);
+        assert_eq!(StopReason::Fault, computer.run_until(|_| false));
+    }
+}
@@ -0,0 +1,264 @@
+#![allow(dead_code)]
+
+use crate::computer::{
+    Intcode, OpDecoder, OP_ADD, OP_ADJUST_RELATIVE_BASE, OP_EQUALS, OP_HALT, OP_JUMP_IF_FALSE,
+    OP_JUMP_IF_TRUE, OP_LESS_THAN, OP_MUL, OP_PARAMETER_BASE, OP_PARAMETER_BASE_POS,
+    OP_STORE_INPUT, OP_WRITE_OUTPUT, PARAM_TYPE_IMMEDIATE, PARAM_TYPE_POSITION,
+    PARAM_TYPE_RELATIVE,
+};
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Position(Intcode),
+    Immediate(Intcode),
+    Relative(Intcode),
+}
+
+impl Operand {
+    fn mode(&self) -> Intcode {
+        match self {
+            Operand::Position(_) => PARAM_TYPE_POSITION,
+            Operand::Immediate(_) => PARAM_TYPE_IMMEDIATE,
+            Operand::Relative(_) => PARAM_TYPE_RELATIVE,
+        }
+    }
+
+    fn value(&self) -> Intcode {
+        match self {
+            Operand::Position(n) | Operand::Immediate(n) | Operand::Relative(n) => *n,
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Position(p) => write!(f, "[{}]", p),
+            Operand::Immediate(n) => write!(f, "#{}", n),
+            Operand::Relative(o) if *o < 0 => write!(f, "[rb-{}]", -o),
+            Operand::Relative(o) => write!(f, "[rb+{}]", o),
+        }
+    }
+}
+
+/// A single statically decoded instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub addr: usize,
+    pub opcode: Intcode,
+    pub operands: Vec<Operand>,
+}
+
+impl Instruction {
+    /// Number of parameters taken by a known opcode.
+    pub fn param_count(opcode: Intcode) -> Option<usize> {
+        match opcode {
+            OP_ADD | OP_MUL | OP_LESS_THAN | OP_EQUALS => Some(3),
+            OP_JUMP_IF_TRUE | OP_JUMP_IF_FALSE => Some(2),
+            OP_STORE_INPUT | OP_WRITE_OUTPUT | OP_ADJUST_RELATIVE_BASE => Some(1),
+            OP_HALT => Some(0),
+            _ => None,
+        }
+    }
+
+    /// Decodes the instruction starting at `addr`, or `None` if the
+    /// words there can't be a valid instruction, including one cut off
+    /// by the end of the program.
+    pub fn decode(program: &[Intcode], addr: usize) -> Option<Self> {
+        let word = *program.get(addr)?;
+        if word < 0 {
+            return None;
+        }
+
+        let op = OpDecoder(word);
+        let opcode = op.opcode();
+        let count = Self::param_count(opcode)?;
+        if word >= OP_PARAMETER_BASE.pow(count as u32 + OP_PARAMETER_BASE_POS - 1)
+            || addr + 1 + count > program.len()
+        {
+            return None;
+        }
+
+        let mut operands = Vec::with_capacity(count);
+        for argno in 0..count {
+            let value = program[addr + 1 + argno];
+            operands.push(match op.param_type(argno as u32) {
+                PARAM_TYPE_POSITION => Operand::Position(value),
+                PARAM_TYPE_IMMEDIATE => Operand::Immediate(value),
+                PARAM_TYPE_RELATIVE => Operand::Relative(value),
+                _ => return None,
+            });
+        }
+
+        let instr = Instruction {
+            addr,
+            opcode,
+            operands,
+        };
+
+        match instr.dest() {
+            Some(Operand::Immediate(_)) => None,
+            _ => Some(instr),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<Intcode> {
+        let mut word = self.opcode;
+        for (argno, operand) in self.operands.iter().enumerate() {
+            word +=
+                operand.mode() * OP_PARAMETER_BASE.pow(argno as u32 + OP_PARAMETER_BASE_POS - 1);
+        }
+
+        let mut words = vec![word];
+        words.extend(self.operands.iter().map(Operand::value));
+        words
+    }
+
+    pub fn size(&self) -> usize {
+        1 + self.operands.len()
+    }
+
+    /// The addresses of the words this instruction occupies.
+    pub fn words(&self) -> std::ops::Range<usize> {
+        self.addr..self.addr + self.size()
+    }
+
+    /// Operand the instruction writes to, if any.
+    pub fn dest(&self) -> Option<Operand> {
+        match self.opcode {
+            OP_ADD | OP_MUL | OP_LESS_THAN | OP_EQUALS => Some(self.operands[2]),
+            OP_STORE_INPUT => Some(self.operands[0]),
+            _ => None,
+        }
+    }
+
+    /// Operands the instruction reads.
+    pub fn sources(&self) -> &[Operand] {
+        match self.opcode {
+            OP_ADD | OP_MUL | OP_LESS_THAN | OP_EQUALS => &self.operands[..2],
+            OP_STORE_INPUT => &[],
+            _ => &self.operands,
+        }
+    }
+
+    pub fn is_jump(&self) -> bool {
+        matches!(self.opcode, OP_JUMP_IF_TRUE | OP_JUMP_IF_FALSE)
+    }
+
+    /// For a jump with an immediate condition, whether it is always
+    /// taken.
+    pub fn jump_always_taken(&self) -> Option<bool> {
+        match (self.opcode, self.operands.first()) {
+            (OP_JUMP_IF_TRUE, Some(Operand::Immediate(n))) => Some(*n != 0),
+            (OP_JUMP_IF_FALSE, Some(Operand::Immediate(n))) => Some(*n == 0),
+            _ => None,
+        }
+    }
+
+    /// Whether control may continue to the next instruction.
+    pub fn falls_through(&self) -> bool {
+        self.opcode != OP_HALT && self.jump_always_taken() != Some(true)
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ops = &self.operands;
+        match self.opcode {
+            OP_ADD if ops[1] == Operand::Immediate(0) => write!(f, "mov {} -> {}", ops[0], ops[2]),
+            OP_ADD if ops[0] == Operand::Immediate(0) => write!(f, "mov {} -> {}", ops[1], ops[2]),
+            OP_MUL if ops[1] == Operand::Immediate(1) => write!(f, "mov {} -> {}", ops[0], ops[2]),
+            OP_ADD => write!(f, "add {}, {} -> {}", ops[0], ops[1], ops[2]),
+            OP_MUL => write!(f, "mul {}, {} -> {}", ops[0], ops[1], ops[2]),
+            OP_LESS_THAN => write!(f, "lt {}, {} -> {}", ops[0], ops[1], ops[2]),
+            OP_EQUALS => write!(f, "eq {}, {} -> {}", ops[0], ops[1], ops[2]),
+            OP_STORE_INPUT => write!(f, "in -> {}", ops[0]),
+            OP_WRITE_OUTPUT => write!(f, "out {}", ops[0]),
+            _ if self.jump_always_taken() == Some(true) => write!(f, "jmp {}", ops[1]),
+            _ if self.jump_always_taken() == Some(false) => write!(f, "nop"),
+            OP_JUMP_IF_TRUE => write!(f, "jt {}, {}", ops[0], ops[1]),
+            OP_JUMP_IF_FALSE => write!(f, "jf {}, {}", ops[0], ops[1]),
+            OP_ADJUST_RELATIVE_BASE => write!(f, "rbo {}", ops[0]),
+            OP_HALT => write!(f, "halt"),
+            x => write!(f, "op{}", x),
+        }
+    }
+}
+
+/// Produces a listing of the program by linear sweep. Words that
+/// don't decode are shown as data, and runs of zeroes are collapsed
+/// to a single line.
+pub fn disassemble(program: &[Intcode]) -> String {
+    let mut sb = String::new();
+    let mut addr = 0;
+
+    while addr < program.len() {
+        if program[addr] == 0 {
+            let run = program[addr..].iter().take_while(|&&n| n == 0).count();
+            if run > 1 {
+                sb += &format!("{:>6}: .zero {}\n", addr, run);
+                addr += run;
+                continue;
+            }
+        }
+
+        match Instruction::decode(program, addr) {
+            Some(instr) => {
+                sb += &format!("{:>6}: {}\n", addr, instr);
+                addr += instr.size();
+            }
+            _ => {
+                sb += &format!("{:>6}: .data {}\n", addr, program[addr]);
+                addr += 1;
+            }
+        }
+    }
+
+    sb
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode_round_trip() {
+        let program = vec![1002, 4, 3, 4, 33, 21101, -1, 7, 3, 99];
+        let instr = Instruction::decode(&program, 0).unwrap();
+        assert_eq!(
+            vec![
+                Operand::Position(4),
+                Operand::Immediate(3),
+                Operand::Position(4)
+            ],
+            instr.operands
+        );
+        assert_eq!(program[0..4], instr.encode()[..]);
+
+        let instr = Instruction::decode(&program, 5).unwrap();
+        assert_eq!(program[5..9], instr.encode()[..]);
+
+        // immediate-mode destination is not a valid instruction
+        assert_eq!(None, Instruction::decode(&[11101, 1, 1, 1], 0));
+
+        // nor is one missing operands at the end of the program
+        assert_eq!(None, Instruction::decode(&[1101, 2, 3], 0));
+    }
+
+    #[test]
+    fn disassemble_listing() {
+        let program = vec![1101, 2, 0, 9, 1105, 1, 9, 0, 0, 0, 99];
+        let expected = [
+            "     0: mov #2 -> [9]",
+            "     4: jmp #9",
+            "     7: .zero 3",
+            "    10: halt",
+        ];
+        assert_eq!(
+            expected.map(|line| format!("{}\n", line)).concat(),
+            disassemble(&program)
+        );
+    }
+}
@@ -0,0 +1,249 @@
+#![allow(dead_code)]
+
+use crate::computer::{Computer, Intcode, StopReason};
+use crate::map::ShipMap;
+use crate::parser;
+
+use std::fmt;
+
+pub const CHECKPOINT: &str = "Security Checkpoint";
+
+/// Instructions a single command may run for before we decide the
+/// program is stuck in a loop. Ordinary commands need a few thousand.
+const COMMAND_BUDGET: usize = 1_000_000;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ExploreError {
+    /// The game ended. Holds the command and the game's last words.
+    Halted(String, String),
+    /// The command never came back asking for another.
+    Looping(String),
+    /// The game said something we couldn't make sense of.
+    Unexpected(String, String),
+    NoRoute(String),
+}
+
+impl fmt::Display for ExploreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExploreError::Halted(command, output) => {
+                write!(f, "game over after `{}`:\n{}", command, output)
+            }
+            ExploreError::Looping(command) => write!(f, "`{}` never finished", command),
+            ExploreError::Unexpected(command, output) => {
+                write!(f, "unexpected reply to `{}`:\n{}", command, output)
+            }
+            ExploreError::NoRoute(room) => write!(f, "no known route to {}", room),
+        }
+    }
+}
+
+impl std::error::Error for ExploreError {}
+
+/// Feeds one command to the droid and collects everything it says
+/// until it asks for the next one.
+pub fn send(computer: &mut Computer, command: &str) -> Result<String, ExploreError> {
+    if !command.is_empty() {
+        computer.buffer_inputs(Computer::ascii_to_intcodes(&format!("{}\n", command)));
+    }
+
+    let mut steps = 0;
+    let stop = loop {
+        match computer.run_until(|_| {
+            steps += 1;
+            steps > COMMAND_BUDGET
+        }) {
+            StopReason::Output => (),
+            reason => break reason,
+        }
+    };
+
+    let output = Computer::intcodes_to_ascii(computer.consume_output_buffer());
+    match stop {
+        StopReason::AwaitingInput => Ok(output),
+        StopReason::Halted => Err(ExploreError::Halted(command.to_owned(), output)),
+        StopReason::Fault => Err(ExploreError::Unexpected(command.to_owned(), output)),
+        _ => Err(ExploreError::Looping(command.to_owned())),
+    }
+}
+
+/// Maps the ship by walking the droid through every door, picking up
+/// every item it can carry safely along the way.
+pub struct Explorer {
+    computer: Computer,
+    here: String,
+    map: ShipMap,
+    inventory: Vec<String>,
+}
+
+impl Explorer {
+    pub fn new(program: Vec<Intcode>) -> Result<Self, ExploreError> {
+        let mut computer = Computer::new(program);
+        let output = send(&mut computer, "")?;
+
+        let mut map = ShipMap::new();
+        let here = map
+            .update(None, "", &parser::parse(&output))
+            .ok_or(ExploreError::Unexpected(String::new(), output))?;
+
+        Ok(Explorer {
+            computer,
+            here,
+            map,
+            inventory: vec![],
+        })
+    }
+
+    /// Tries each item in the current room on a copy of the game
+    /// first, and only picks it up for real if the copy could still
+    /// carry on afterwards.
+    fn collect_items(&mut self) -> Result<(), ExploreError> {
+        // a room we can't find or can't leave means we misread the game
+        let lost =
+            || ExploreError::Unexpected(String::new(), format!("no way out of {}", self.here));
+        let room = self.map.room(&self.here).ok_or_else(lost)?;
+        let items = room.items.clone();
+        let door = room.doors.first().ok_or_else(lost)?.clone();
+
+        for item in items {
+            if self.map.is_hazard(&item) {
+                continue;
+            }
+
+            let take = format!("take {}", item);
+            let mut fork = self.computer.clone();
+            let safe = send(&mut fork, &take).is_ok()
+                && send(&mut fork, &door)
+                    .is_ok_and(|output| parser::parse(&output).room().is_some());
+
+            if safe {
+                self.command(&take)?;
+                self.inventory.push(item);
+            } else {
+                self.map.mark_hazard(&item);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn command(&mut self, command: &str) -> Result<(), ExploreError> {
+        let output = send(&mut self.computer, command)?;
+        let result = parser::parse(&output);
+        self.here = self
+            .map
+            .update(Some(&self.here), command, &result)
+            .ok_or_else(|| ExploreError::Unexpected(command.to_owned(), output.clone()))?;
+        Ok(())
+    }
+
+    /// Visits every room reachable without crossing the pressure
+    /// floor.
+    pub fn explore(&mut self) -> Result<(), ExploreError> {
+        loop {
+            self.collect_items()?;
+
+            let map = &self.map;
+            let Some((path, door)) = map.search(&self.here, |room| {
+                map.unexplored(room).next().map(str::to_owned)
+            }) else {
+                return Ok(());
+            };
+            for step in path {
+                self.command(&step)?;
+            }
+            self.command(&door)?;
+        }
+    }
+
+    pub fn floor(&self) -> Option<(&str, &str)> {
+        self.map.floor()
+    }
+
+    pub fn here(&self) -> &str {
+        &self.here
+    }
+
+    pub fn inventory(&self) -> &[String] {
+        &self.inventory
+    }
+
+    pub fn map(&self) -> &ShipMap {
+        &self.map
+    }
+
+    /// Sends a raw command to the droid.
+    pub fn send(&mut self, command: &str) -> Result<String, ExploreError> {
+        send(&mut self.computer, command)
+    }
+
+    pub fn walk_to(&mut self, room: &str) -> Result<(), ExploreError> {
+        let path = self
+            .map
+            .route(&self.here, room)
+            .ok_or_else(|| ExploreError::NoRoute(room.to_owned()))?;
+
+        for door in path {
+            self.command(&door)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::fs;
+
+    /// A game that prints `text` and then waits for input forever.
+    fn scripted(text: &str) -> Vec<Intcode> {
+        let mut program: Vec<Intcode> = Computer::ascii_to_intcodes(text)
+            .into_iter()
+            .flat_map(|c| [104, c])
+            .collect();
+        let wait = program.len() as Intcode;
+        program.extend([3, 0, 1105, 1, wait]);
+        program
+    }
+
+    #[test]
+    fn room_without_doors() {
+        let room = "\n\n\n== Closet ==\nDark.\n\nItems here:\n- mug\n\nCommand?\n";
+        let mut explorer = Explorer::new(scripted(room)).unwrap();
+        assert_eq!(
+            Err(ExploreError::Unexpected(
+                String::new(),
+                "no way out of Closet".to_owned()
+            )),
+            explorer.explore()
+        );
+    }
+
+    #[test]
+    fn explore_ship() {
+        let program = Computer::parse_program(&fs::read_to_string("input.txt").unwrap());
+        let mut explorer = Explorer::new(program).unwrap();
+        explorer.explore().unwrap();
+        explorer.walk_to(CHECKPOINT).unwrap();
+
+        let mut inventory = explorer.inventory().to_vec();
+        inventory.sort();
+        assert_eq!(
+            vec![
+                "astrolabe",
+                "candy cane",
+                "dark matter",
+                "hologram",
+                "klein bottle",
+                "ornament",
+                "tambourine",
+                "whirled peas",
+            ],
+            inventory
+        );
+        assert_eq!(5, explorer.map().hazards().count());
+        assert_eq!(CHECKPOINT, explorer.here());
+        assert_eq!(Some((CHECKPOINT, "north")), explorer.floor());
+    }
+}
@@ -0,0 +1,326 @@
+#![allow(dead_code)]
+
+use crate::parser::{CommandResult, Room};
+
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+pub const DIRECTIONS: [&str; 4] = ["north", "south", "east", "west"];
+
+pub fn opposite(door: &str) -> &str {
+    match door {
+        "north" => "south",
+        "south" => "north",
+        "east" => "west",
+        "west" => "east",
+        x => x,
+    }
+}
+
+/// What the droid has learned about the ship so far.
+#[derive(Debug, Clone, Default)]
+pub struct ShipMap {
+    rooms: HashMap<String, Room>,
+    exits: HashMap<(String, String), String>,
+    floor: Option<(String, String)>,
+    floor_room: Option<String>,
+    hazards: BTreeSet<String>,
+}
+
+impl ShipMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn exit(&self, room: &str, door: &str) -> Option<&str> {
+        self.exits
+            .get(&(room.to_owned(), door.to_owned()))
+            .map(String::as_str)
+    }
+
+    /// Every known `(room, door, destination)`.
+    pub fn exits(&self) -> impl Iterator<Item = (&str, &str, &str)> {
+        self.exits
+            .iter()
+            .map(|((room, door), to)| (room.as_str(), door.as_str(), to.as_str()))
+    }
+
+    /// The checkpoint and the door from it onto the pressure floor,
+    /// once the droid has been bounced off it.
+    pub fn floor(&self) -> Option<(&str, &str)> {
+        self.floor
+            .as_ref()
+            .map(|(room, door)| (room.as_str(), door.as_str()))
+    }
+
+    /// The name of the pressure floor itself.
+    pub fn floor_room(&self) -> Option<&str> {
+        self.floor_room.as_deref()
+    }
+
+    pub fn hazards(&self) -> impl Iterator<Item = &str> {
+        self.hazards.iter().map(String::as_str)
+    }
+
+    pub fn is_hazard(&self, item: &str) -> bool {
+        self.hazards.contains(item)
+    }
+
+    pub fn mark_hazard(&mut self, item: &str) {
+        self.hazards.insert(item.to_owned());
+    }
+
+    pub fn room(&self, name: &str) -> Option<&Room> {
+        self.rooms.get(name)
+    }
+
+    pub fn rooms(&self) -> impl Iterator<Item = &Room> {
+        self.rooms.values()
+    }
+
+    /// Doors leading from `from` to `to` over known exits.
+    pub fn route(&self, from: &str, to: &str) -> Option<Vec<String>> {
+        self.search(from, |room| (room == to).then_some(()))
+            .map(|(path, ())| path)
+    }
+
+    /// Breadth-first search over known exits from `from`, stopping at
+    /// the first room for which `found` returns something.
+    pub fn search<T>(
+        &self,
+        from: &str,
+        found: impl Fn(&str) -> Option<T>,
+    ) -> Option<(Vec<String>, T)> {
+        let mut queue = VecDeque::from([(from.to_owned(), vec![])]);
+        let mut seen = HashSet::from([from.to_owned()]);
+
+        while let Some((room, path)) = queue.pop_front() {
+            if let Some(x) = found(&room) {
+                return Some((path, x));
+            }
+
+            for door in self.rooms.get(&room).map_or(&[][..], |r| &r.doors) {
+                if let Some(next) = self.exit(&room, door) {
+                    if seen.insert(next.to_owned()) {
+                        let mut path = path.clone();
+                        path.push(door.clone());
+                        queue.push_back((next.to_owned(), path));
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Renders the map as a Graphviz digraph, for `dot -Tsvg`. Rooms
+    /// list their items, with dangerous ones in red, and the checkpoint
+    /// and pressure floor stand out. Each pair of doors between two
+    /// rooms is drawn as one edge, labelled from the first room's side.
+    pub fn to_dot(&self) -> String {
+        let mut rooms: Vec<&Room> = self.rooms.values().collect();
+        rooms.sort_by(|a, b| a.name.cmp(&b.name));
+        let checkpoint = self.floor().map(|(room, _)| room);
+
+        let mut sb = String::from("digraph ship {\n    node [shape=box];\n");
+
+        for room in rooms {
+            let mut label = format!("<B>{}</B>", escape_html(&room.name));
+            for item in &room.items {
+                if self.is_hazard(item) {
+                    label += &format!("<BR/><FONT COLOR=\"red\">{}</FONT>", escape_html(item));
+                } else {
+                    label += &format!("<BR/>{}", escape_html(item));
+                }
+            }
+
+            let style = if Some(room.name.as_str()) == checkpoint {
+                ", style=filled, fillcolor=gold"
+            } else if Some(&room.name) == self.floor_room.as_ref() {
+                ", style=filled, fillcolor=tomato"
+            } else {
+                ""
+            };
+            sb += &format!("    {} [label=<{}>{}];\n", dot_id(&room.name), label, style);
+        }
+
+        let mut exits: Vec<_> = self.exits().collect();
+        exits.sort();
+        for &(room, door, to) in &exits {
+            let reverse = (to, opposite(door), room);
+            if reverse < (room, door, to) && exits.binary_search(&reverse).is_ok() {
+                continue;
+            }
+            sb += &format!(
+                "    {} -> {} [label={}, dir=both];\n",
+                dot_id(room),
+                dot_id(to),
+                dot_id(door)
+            );
+        }
+
+        if let (Some((room, door)), Some(to)) = (self.floor(), self.floor_room()) {
+            sb += &format!(
+                "    {} -> {} [label={}, color=red, style=dashed];\n",
+                dot_id(room),
+                dot_id(to),
+                dot_id(door)
+            );
+        }
+
+        sb += "}\n";
+        sb
+    }
+
+    /// Doors out of `room` that we haven't been through yet, not
+    /// counting the one onto the pressure floor.
+    pub fn unexplored<'a>(&'a self, room: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.rooms
+            .get(room)
+            .into_iter()
+            .flat_map(|r| r.doors.iter())
+            .filter(move |&door| {
+                let key = (room.to_owned(), door.clone());
+                !self.exits.contains_key(&key) && self.floor.as_ref() != Some(&key)
+            })
+            .map(String::as_str)
+    }
+
+    /// Updates the map from the droid's reply to `command`, which was
+    /// given in room `here`, and returns the room the droid ends up in.
+    pub fn update(
+        &mut self,
+        here: Option<&str>,
+        command: &str,
+        result: &CommandResult,
+    ) -> Option<String> {
+        for room in &result.rooms {
+            self.rooms.insert(room.name.clone(), room.clone());
+        }
+
+        let now = result.room().map(|r| r.name.as_str()).or(here)?.to_owned();
+
+        if let (Some(from), Some(_)) = (here, result.room()) {
+            if DIRECTIONS.contains(&command) {
+                if from == now && result.rooms.len() > 1 {
+                    // Bounced straight back: this is the pressure
+                    // floor, which we can't cross until we carry the
+                    // right weight.
+                    self.floor = Some((from.to_owned(), command.to_owned()));
+                    self.floor_room = Some(result.rooms[0].name.clone());
+                } else if from != now {
+                    let key = (from.to_owned(), command.to_owned());
+                    self.exits.insert(key, now.clone());
+                    let key = (now.clone(), opposite(command).to_owned());
+                    self.exits.insert(key, from.to_owned());
+                }
+            }
+        }
+
+        if let Some(room) = self.rooms.get_mut(&now) {
+            room.items.retain(|i| !result.taken.contains(i));
+            room.items.extend(result.dropped.iter().cloned());
+        }
+
+        Some(now)
+    }
+}
+
+/// Quotes `s` as a Graphviz ID. Inside quotes only `"` and `\` need
+/// escaping.
+fn dot_id(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::parser;
+
+    #[test]
+    fn update_tracks_exits_and_items() {
+        let mut map = ShipMap::new();
+        let start = "\n\n\n== Hull Breach ==\nA hole.\n\nDoors here lead:\n- north\n\nCommand?\n";
+        let here = map.update(None, "", &parser::parse(start)).unwrap();
+        assert_eq!("Hull Breach", here);
+        assert_eq!(vec!["north"], map.unexplored(&here).collect::<Vec<_>>());
+
+        let north = "\n\n\n== Kitchen ==\nPots.\n\nDoors here lead:\n- south\n\n\
+                     Items here:\n- mug\n\nCommand?\n";
+        let here = map
+            .update(Some(&here), "north", &parser::parse(north))
+            .unwrap();
+        assert_eq!("Kitchen", here);
+        assert_eq!(Some("Hull Breach"), map.exit("Kitchen", "south"));
+        assert_eq!(0, map.unexplored("Hull Breach").count());
+        assert_eq!(
+            Some(vec!["north".to_owned()]),
+            map.route("Hull Breach", "Kitchen")
+        );
+
+        let take = "\nYou take the mug.\n\nCommand?\n";
+        let here = map
+            .update(Some(&here), "take mug", &parser::parse(take))
+            .unwrap();
+        assert_eq!("Kitchen", here);
+        assert!(map.room("Kitchen").unwrap().items.is_empty());
+    }
+
+    #[test]
+    fn dot_highlights_checkpoint() {
+        let mut map = ShipMap::new();
+        let checkpoint = "\n\n\n== Security Checkpoint ==\nScales.\n\nDoors here lead:\n- north\n\
+                          - west\n\nItems here:\n- escape pod\n\nCommand?\n";
+        let here = map.update(None, "", &parser::parse(checkpoint)).unwrap();
+
+        let west = "\n\n\n== Hallway ==\nLong.\n\nDoors here lead:\n- east\n\nItems here:\n\
+                    - mug & spoon\n\nCommand?\n";
+        let here = map
+            .update(Some(&here), "west", &parser::parse(west))
+            .unwrap();
+        let here = map
+            .update(Some(&here), "east", &parser::parse(checkpoint))
+            .unwrap();
+
+        let floor = "\n\n\n== Pressure-Sensitive Floor ==\nAnalyzing...\n\nDoors here lead:\n\
+                     - south\n\nA loud, robotic voice says \"Alert! Droids on this ship are \
+                     lighter than the detected value!\" and you are ejected back to the \
+                     checkpoint.\n";
+        let output = format!("{}{}", floor, checkpoint);
+        map.update(Some(&here), "north", &parser::parse(&output));
+        map.mark_hazard("escape pod");
+
+        let expected = [
+            "digraph ship {",
+            "    node [shape=box];",
+            "    \"Hallway\" [label=<<B>Hallway</B><BR/>mug &amp; spoon>];",
+            "    \"Pressure-Sensitive Floor\" [label=<<B>Pressure-Sensitive Floor</B>>, \
+             style=filled, fillcolor=tomato];",
+            "    \"Security Checkpoint\" [label=<<B>Security Checkpoint</B>\
+             <BR/><FONT COLOR=\"red\">escape pod</FONT>>, style=filled, fillcolor=gold];",
+            "    \"Hallway\" -> \"Security Checkpoint\" [label=\"east\", dir=both];",
+            "    \"Security Checkpoint\" -> \"Pressure-Sensitive Floor\" [label=\"north\", \
+             color=red, style=dashed];",
+            "}",
+        ];
+        assert_eq!(
+            expected.map(|line| format!("{}\n", line)).concat(),
+            map.to_dot()
+        );
+    }
+
+    #[test]
+    fn dot_ids_escape_only_quotes_and_backslashes() {
+        assert_eq!(r#""Hull Breach""#, dot_id("Hull Breach"));
+        assert_eq!(
+            "\"say \\\"hi\\\" \\\\ to the café\t\"",
+            dot_id("say \"hi\" \\ to the café\t")
+        );
+    }
+}
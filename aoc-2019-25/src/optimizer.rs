@@ -0,0 +1,485 @@
+#![allow(dead_code)]
+
+use crate::computer::{
+    Computer, Intcode, StopReason, OP_ADD, OP_EQUALS, OP_JUMP_IF_TRUE, OP_LESS_THAN, OP_MUL,
+};
+use crate::disassembler::{Instruction, Operand};
+
+use std::collections::{BTreeMap, HashSet};
+
+/// Upper bound on rewrite passes; each pass can only enable a little
+/// more folding, so real programs settle well before this.
+const MAX_PASSES: usize = 32;
+
+/// Instructions either program may run for between two inputs before
+/// `verify` decides it's stuck.
+const VERIFY_BUDGET: usize = 10_000_000;
+
+/// What a static look at the program could learn about it.
+///
+/// Intcode programs may write to their own code, so every rewrite is
+/// guarded by the `written` and `read` sets: an instruction is only
+/// changed if none of its words can ever be written or read as data.
+struct Analysis {
+    /// Instructions reachable from address 0, by address.
+    reachable: BTreeMap<usize, Instruction>,
+    /// Exact addresses that may be written.
+    written: HashSet<usize>,
+    /// Exact addresses that may be read as data.
+    read: HashSet<usize>,
+    /// Some write lands at an address we can't compute.
+    wild_writes: bool,
+    /// Some read comes from an address we can't compute.
+    wild_reads: bool,
+    /// Some jump target is read from memory, so `reachable` may be
+    /// incomplete.
+    indirect_jumps: bool,
+    /// The walk ran into a word that isn't an instruction, or into an
+    /// instruction that may be written, so `reachable` may not be what
+    /// actually runs.
+    unknown_code: bool,
+}
+
+impl Analysis {
+    fn new(program: &[Intcode], relative_is_stack: bool) -> Self {
+        let mut analysis = Analysis {
+            reachable: BTreeMap::new(),
+            written: HashSet::new(),
+            read: HashSet::new(),
+            wild_writes: false,
+            wild_reads: false,
+            indirect_jumps: false,
+            unknown_code: false,
+        };
+
+        let mut pending = vec![0];
+        while let Some(addr) = pending.pop() {
+            if analysis.reachable.contains_key(&addr) {
+                continue;
+            }
+            let Some(instr) = Instruction::decode(program, addr) else {
+                // most likely code the program writes before running it
+                analysis.unknown_code = true;
+                continue;
+            };
+
+            if instr.falls_through() {
+                pending.push(addr + instr.size());
+            }
+            if instr.is_jump() && instr.jump_always_taken() != Some(false) {
+                match instr.operands[1] {
+                    Operand::Immediate(target) if target >= 0 => pending.push(target as usize),
+                    _ => analysis.indirect_jumps = true,
+                }
+            }
+
+            analysis.reachable.insert(addr, instr);
+        }
+
+        // Code reached through a computed jump or written at runtime
+        // could be anywhere, so then gather writes from an instruction
+        // decoded at every address. Whether reachable code is written
+        // is only known once the writes are, hence the second go.
+        let mut everywhere = analysis.indirect_jumps || analysis.unknown_code;
+        loop {
+            let writers: Vec<Instruction> = if everywhere {
+                (0..program.len())
+                    .filter_map(|addr| Instruction::decode(program, addr))
+                    .collect()
+            } else {
+                analysis.reachable.values().cloned().collect()
+            };
+
+            for instr in &writers {
+                match instr.dest() {
+                    Some(Operand::Position(p)) if p >= 0 => {
+                        analysis.written.insert(p as usize);
+                    }
+                    Some(Operand::Relative(_)) if !relative_is_stack => analysis.wild_writes = true,
+                    // a negative address stops the machine before the write
+                    _ => (),
+                }
+
+                for operand in instr.sources() {
+                    match operand {
+                        Operand::Position(p) if *p >= 0 => {
+                            analysis.read.insert(*p as usize);
+                        }
+                        Operand::Relative(_) if !relative_is_stack => analysis.wild_reads = true,
+                        _ => (),
+                    }
+                }
+            }
+
+            analysis.unknown_code |= analysis
+                .reachable
+                .values()
+                .flat_map(Instruction::words)
+                .any(|a| analysis.written.contains(&a));
+            if everywhere || !analysis.unknown_code {
+                break;
+            }
+            everywhere = true;
+        }
+
+        analysis
+    }
+
+    /// Whether the words of `instr` are only ever executed, so that
+    /// rewriting them can't be observed.
+    fn is_stable(&self, instr: &Instruction) -> bool {
+        !self.wild_writes
+            && !self.wild_reads
+            && instr
+                .words()
+                .all(|a| !self.written.contains(&a) && !self.read.contains(&a))
+    }
+
+    /// Whether the word at `addr` keeps its initial value for the
+    /// whole run.
+    fn is_constant(&self, addr: usize) -> bool {
+        !self.wild_writes
+            && !self.written.contains(&addr)
+            && !self.reachable.values().any(|i| i.words().contains(&addr))
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Optimized {
+    pub program: Vec<Intcode>,
+    /// Operands or whole instructions replaced by constants.
+    pub folded: usize,
+    /// Jumps that were made unconditional or retargeted.
+    pub threaded: usize,
+    /// Unreachable words cleared to zero.
+    pub removed: usize,
+}
+
+#[derive(Default)]
+pub struct Optimizer {
+    relative_is_stack: bool,
+}
+
+impl Optimizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Treats relative-mode operands as stack accesses that never touch
+    /// the program image. This holds for compiled code and for the
+    /// puzzle programs, but it can't be proven statically, so it's off
+    /// by default.
+    pub fn relative_is_stack(mut self, yes: bool) -> Self {
+        self.relative_is_stack = yes;
+        self
+    }
+
+    pub fn optimize(&self, program: &[Intcode]) -> Optimized {
+        let mut result = Optimized {
+            program: program.to_vec(),
+            ..Default::default()
+        };
+
+        for _ in 0..MAX_PASSES {
+            let analysis = Analysis::new(&result.program, self.relative_is_stack);
+            let folded = self.fold(&analysis, &mut result.program);
+            let threaded = self.thread(&analysis, &mut result.program);
+            result.folded += folded;
+            result.threaded += threaded;
+            if folded + threaded == 0 {
+                break;
+            }
+        }
+
+        let analysis = Analysis::new(&result.program, self.relative_is_stack);
+        result.removed = self.remove_dead(&analysis, &mut result.program);
+
+        result
+    }
+
+    /// Replaces reads of constant memory with immediates, and
+    /// arithmetic on two immediates with a store of the result.
+    fn fold(&self, analysis: &Analysis, program: &mut [Intcode]) -> usize {
+        let mut count = 0;
+
+        for instr in analysis.reachable.values() {
+            if !analysis.is_stable(instr) {
+                continue;
+            }
+
+            let mut new = instr.clone();
+            let nsources = instr.sources().len();
+            for operand in &mut new.operands[..nsources] {
+                if let Operand::Position(p) = *operand {
+                    if p >= 0 && analysis.is_constant(p as usize) {
+                        *operand =
+                            Operand::Immediate(program.get(p as usize).copied().unwrap_or(0));
+                        count += 1;
+                    }
+                }
+            }
+
+            if let [Operand::Immediate(a), Operand::Immediate(b), dest] = new.operands[..] {
+                let value = match new.opcode {
+                    OP_ADD if a == 0 || b == 0 => None,
+                    OP_ADD => a.checked_add(b),
+                    OP_MUL => a.checked_mul(b),
+                    OP_LESS_THAN => Some(Intcode::from(a < b)),
+                    OP_EQUALS => Some(Intcode::from(a == b)),
+                    _ => None,
+                };
+                if let Some(value) = value {
+                    new.opcode = OP_ADD;
+                    new.operands = vec![Operand::Immediate(value), Operand::Immediate(0), dest];
+                    count += 1;
+                }
+            }
+
+            if new != *instr {
+                program[instr.words()].copy_from_slice(&new.encode());
+            }
+        }
+
+        count
+    }
+
+    /// Makes jumps with constant true conditions into plain `jmp`s and
+    /// points jumps that land on another `jmp` at its final target.
+    fn thread(&self, analysis: &Analysis, program: &mut [Intcode]) -> usize {
+        let mut count = 0;
+
+        let final_target = |mut target: Intcode| {
+            let mut seen = HashSet::new();
+            while let Some(next) = usize::try_from(target)
+                .ok()
+                .and_then(|t| analysis.reachable.get(&t))
+                .filter(|i| analysis.is_stable(i) && i.jump_always_taken() == Some(true))
+            {
+                if !seen.insert(target) {
+                    break;
+                }
+                match next.operands[1] {
+                    Operand::Immediate(t) => target = t,
+                    _ => break,
+                }
+            }
+            target
+        };
+
+        for instr in analysis.reachable.values() {
+            if !analysis.is_stable(instr) || instr.jump_always_taken() != Some(true) {
+                continue;
+            }
+
+            let Operand::Immediate(target) = instr.operands[1] else {
+                continue;
+            };
+
+            let new = Instruction {
+                addr: instr.addr,
+                opcode: OP_JUMP_IF_TRUE,
+                operands: vec![
+                    Operand::Immediate(1),
+                    Operand::Immediate(final_target(target)),
+                ],
+            };
+            if new != *instr {
+                program[instr.words()].copy_from_slice(&new.encode());
+                count += 1;
+            }
+        }
+
+        count
+    }
+
+    /// Zeroes words that are never executed, read or written. Only done
+    /// when all of the code and every jump target is known.
+    fn remove_dead(&self, analysis: &Analysis, program: &mut [Intcode]) -> usize {
+        if analysis.unknown_code
+            || analysis.indirect_jumps
+            || analysis.wild_reads
+            || analysis.wild_writes
+        {
+            return 0;
+        }
+
+        let live: HashSet<usize> = analysis
+            .reachable
+            .values()
+            .flat_map(Instruction::words)
+            .chain(analysis.read.iter().copied())
+            .chain(analysis.written.iter().copied())
+            .collect();
+
+        let mut count = 0;
+        for (addr, word) in program.iter_mut().enumerate() {
+            if *word != 0 && !live.contains(&addr) {
+                *word = 0;
+                count += 1;
+            }
+        }
+
+        count
+    }
+}
+
+/// The first point where an optimized program stopped behaving like
+/// the original.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// How many inputs had been consumed.
+    pub inputs_consumed: usize,
+    pub original: Vec<Intcode>,
+    pub optimized: Vec<Intcode>,
+    /// Why each program stopped.
+    pub original_stop: StopReason,
+    pub optimized_stop: StopReason,
+}
+
+/// Runs `computer` until it wants an input it hasn't got, halts,
+/// faults or runs out of budget, and collects what it printed.
+fn run_to_stop(computer: &mut Computer) -> (StopReason, Vec<Intcode>) {
+    let mut steps = 0;
+    let stop = loop {
+        match computer.run_until(|_| {
+            steps += 1;
+            steps > VERIFY_BUDGET
+        }) {
+            StopReason::Output => (),
+            StopReason::Condition => break StopReason::BudgetExhausted,
+            reason => break reason,
+        }
+    };
+    (stop, computer.consume_output_buffer().collect())
+}
+
+/// Runs both programs side by side, feeding them the same recorded
+/// inputs one at a time, and checks that they produce the same
+/// outputs and stop in the same state after each input. A program
+/// that faults or runs too long counts as diverging, even if both do.
+pub fn verify(
+    original: &[Intcode],
+    optimized: &[Intcode],
+    inputs: &[Intcode],
+) -> Result<(), Divergence> {
+    let mut a = Computer::new(original.to_vec());
+    let mut b = Computer::new(optimized.to_vec());
+
+    let mut consumed = 0;
+    loop {
+        let (stop_a, out_a) = run_to_stop(&mut a);
+        let (stop_b, out_b) = run_to_stop(&mut b);
+        let settled = matches!(stop_a, StopReason::AwaitingInput | StopReason::Halted);
+        if out_a != out_b || stop_a != stop_b || !settled {
+            return Err(Divergence {
+                inputs_consumed: consumed,
+                original: out_a,
+                optimized: out_b,
+                original_stop: stop_a,
+                optimized_stop: stop_b,
+            });
+        }
+
+        if stop_a == StopReason::Halted || consumed == inputs.len() {
+            return Ok(());
+        }
+
+        a.buffer_input(inputs[consumed]);
+        b.buffer_input(inputs[consumed]);
+        consumed += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::fs;
+
+    #[test]
+    fn fold_constants() {
+        // out ([13] + 3) * [14]; halt
+        let program = vec![1001, 13, 3, 15, 2, 15, 14, 16, 4, 16, 99, 0, 0, 5, 7, 0, 0];
+        let optimized = Optimizer::new().optimize(&program);
+
+        // [13] is never written, so the add becomes a store
+        assert_eq!(vec![1101, 8, 0, 15], optimized.program[0..4]);
+        assert_eq!(Ok(()), verify(&program, &optimized.program, &[]));
+        assert!(optimized.folded > 0);
+    }
+
+    #[test]
+    fn guards_self_modifying_code() {
+        // the first instruction overwrites the operand of the second
+        let program = vec![1101, 7, 0, 5, 104, 0, 99];
+        let optimized = Optimizer::new().optimize(&program);
+        assert_eq!(program[4..], optimized.program[4..]);
+        assert_eq!(Ok(()), verify(&program, &optimized.program, &[]));
+    }
+
+    #[test]
+    fn thread_jumps_and_remove_dead_code() {
+        // jt #1, #6; (dead) out #5; jmp #9; (dead) halt; out #1; halt
+        let program = vec![1105, 1, 6, 104, 5, 99, 1105, 1, 9, 104, 1, 99];
+        let optimized = Optimizer::new().optimize(&program);
+        assert_eq!(vec![1105, 1, 9], optimized.program[0..3]);
+        assert_eq!(vec![0, 0, 0], optimized.program[3..6]);
+        assert_eq!(1, optimized.threaded);
+        assert_eq!(Ok(()), verify(&program, &optimized.program, &[]));
+    }
+
+    #[test]
+    fn verify_detects_divergence() {
+        let program = vec![3, 9, 4, 9, 3, 9, 4, 9, 99, 0];
+        let broken = vec![3, 9, 4, 9, 3, 9, 104, 0, 99, 0];
+        assert_eq!(
+            Err(Divergence {
+                inputs_consumed: 2,
+                original: vec![8],
+                optimized: vec![0],
+                original_stop: StopReason::Halted,
+                optimized_stop: StopReason::Halted,
+            }),
+            verify(&program, &broken, &[7, 8])
+        );
+    }
+
+    #[test]
+    fn leaves_code_written_at_runtime() {
+        // the first instruction writes `out #42` over the zero at 4
+        let program = vec![1101, 104, 0, 4, 0, 42, 99];
+        let optimized = Optimizer::new().optimize(&program);
+        assert_eq!(program, optimized.program);
+        assert_eq!(0, optimized.removed);
+        assert_eq!(Ok(()), verify(&program, &optimized.program, &[]));
+
+        // what the optimizer used to make of it
+        let broken = vec![1101, 104, 0, 4, 0, 0, 0];
+        assert_eq!(
+            Err(Divergence {
+                inputs_consumed: 0,
+                original: vec![42],
+                optimized: vec![0],
+                original_stop: StopReason::Halted,
+                optimized_stop: StopReason::Fault,
+            }),
+            verify(&program, &broken, &[])
+        );
+    }
+
+    #[test]
+    fn leaves_truncated_instructions_alone() {
+        // the second add runs off the end of the program
+        let program = vec![1101, 1, 1, 9, 1101, 2, 3];
+        let optimized = Optimizer::new().optimize(&program);
+        assert_eq!(program[4..], optimized.program[4..]);
+    }
+
+    #[test]
+    fn adventure_still_runs() {
+        let program = Computer::parse_program(&fs::read_to_string("input.txt").unwrap());
+        let optimized = Optimizer::new().relative_is_stack(true).optimize(&program);
+        let inputs = Computer::ascii_to_intcodes("north\nsouth\ninv\n");
+        assert_eq!(Ok(()), verify(&program, &optimized.program, &inputs));
+    }
+}
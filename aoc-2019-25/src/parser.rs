@@ -0,0 +1,206 @@
+#![allow(dead_code)]
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Room {
+    pub name: String,
+    pub description: String,
+    pub doors: Vec<String>,
+    pub items: Vec<String>,
+}
+
+/// What the pressure-sensitive floor decided about the droid.
+///
+/// The game words it from the other droids' point of view: "Droids
+/// on this ship are heavier than the detected value" means this one is
+/// too light.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alert {
+    TooHeavy,
+    TooLight,
+}
+
+/// Everything the droid reported in response to one or more commands.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandResult {
+    /// Rooms described, in order. Being ejected from the pressure
+    /// floor describes two.
+    pub rooms: Vec<Room>,
+    pub taken: Vec<String>,
+    pub dropped: Vec<String>,
+    /// Set if the output included an inventory listing.
+    pub inventory: Option<Vec<String>>,
+    pub alert: Option<Alert>,
+    pub password: Option<String>,
+    /// Lines we didn't otherwise recognize, such as "You can't go that
+    /// way."
+    pub messages: Vec<String>,
+    /// Whether the droid finished by asking for another command.
+    pub prompt: bool,
+}
+
+impl CommandResult {
+    /// The room the droid ended up in, if the output described one.
+    pub fn room(&self) -> Option<&Room> {
+        self.rooms.last()
+    }
+}
+
+enum List {
+    Doors,
+    Items,
+    Inventory,
+}
+
+pub fn parse(output: &str) -> CommandResult {
+    let mut result = CommandResult::default();
+    let mut list = None;
+    let mut in_description = false;
+
+    for line in output.lines() {
+        if line.is_empty() {
+            list = None;
+            in_description = false;
+            continue;
+        }
+
+        if let Some(entry) = line.strip_prefix("- ") {
+            match (&list, result.rooms.last_mut()) {
+                (Some(List::Doors), Some(room)) => room.doors.push(entry.to_owned()),
+                (Some(List::Items), Some(room)) => room.items.push(entry.to_owned()),
+                (Some(List::Inventory), _) => result
+                    .inventory
+                    .get_or_insert_with(Vec::new)
+                    .push(entry.to_owned()),
+                _ => result.messages.push(line.to_owned()),
+            }
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix("== ").and_then(|l| l.strip_suffix(" ==")) {
+            result.rooms.push(Room {
+                name: name.to_owned(),
+                ..Room::default()
+            });
+            in_description = true;
+        } else if in_description {
+            let room = result.rooms.last_mut().unwrap();
+            if !room.description.is_empty() {
+                room.description += "\n";
+            }
+            room.description += line;
+        } else if line == "Doors here lead:" {
+            list = Some(List::Doors);
+        } else if line == "Items here:" {
+            list = Some(List::Items);
+        } else if line == "Items in your inventory:" {
+            list = Some(List::Inventory);
+            result.inventory = Some(vec![]);
+        } else if line == "You aren't carrying any items." {
+            result.inventory = Some(vec![]);
+        } else if line == "Command?" {
+            result.prompt = true;
+        } else if let Some(item) = between(line, "You take the ", ".") {
+            result.taken.push(item.to_owned());
+        } else if let Some(item) = between(line, "You drop the ", ".") {
+            result.dropped.push(item.to_owned());
+        } else if line.contains("Droids on this ship are heavier than the detected value") {
+            result.alert = Some(Alert::TooLight);
+        } else if line.contains("Droids on this ship are lighter than the detected value") {
+            result.alert = Some(Alert::TooHeavy);
+        } else if let Some(password) = password(line) {
+            result.password = Some(password.to_owned());
+            result.messages.push(line.to_owned());
+        } else {
+            result.messages.push(line.to_owned());
+        }
+    }
+
+    result
+}
+
+fn between<'a>(line: &'a str, prefix: &str, suffix: &str) -> Option<&'a str> {
+    line.strip_prefix(prefix)?.strip_suffix(suffix)
+}
+
+/// Santa's "... get in by typing 134349952 on the keypad ..." line.
+fn password(line: &str) -> Option<&str> {
+    let (_, rest) = line.split_once("typing ")?;
+    let (password, _) = rest.split_once(' ')?;
+    Some(password)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn strings(s: &[&str]) -> Vec<String> {
+        s.iter().map(|&s| s.to_owned()).collect()
+    }
+
+    #[test]
+    fn parse_room() {
+        let result = parse(
+            "\n\n\n== Observatory ==\nThere are a few telescopes; they're all bolted down, \
+             though.\n\nDoors here lead:\n- north\n- east\n\nItems here:\n- infinite loop\n\n\
+             Command?\n",
+        );
+        assert_eq!(
+            Some(&Room {
+                name: "Observatory".to_owned(),
+                description: "There are a few telescopes; they're all bolted down, though."
+                    .to_owned(),
+                doors: strings(&["north", "east"]),
+                items: strings(&["infinite loop"]),
+            }),
+            result.room()
+        );
+        assert!(result.prompt);
+        assert_eq!(None, result.alert);
+        assert!(result.messages.is_empty());
+    }
+
+    #[test]
+    fn parse_items() {
+        let result = parse(
+            "\nYou take the whirled peas.\n\nCommand?\n\nYou drop the mug.\n\nCommand?\n\n\
+             Items in your inventory:\n- whirled peas\n- hologram\n\nCommand?\n",
+        );
+        assert_eq!(strings(&["whirled peas"]), result.taken);
+        assert_eq!(strings(&["mug"]), result.dropped);
+        assert_eq!(
+            Some(strings(&["whirled peas", "hologram"])),
+            result.inventory
+        );
+
+        let result = parse("\nYou aren't carrying any items.\n\nCommand?\n");
+        assert_eq!(Some(vec![]), result.inventory);
+
+        let result = parse("\nYou don't see that item here.\n\nCommand?\n");
+        assert_eq!(strings(&["You don't see that item here."]), result.messages);
+        assert_eq!(None, result.inventory);
+    }
+
+    #[test]
+    fn parse_checkpoint() {
+        let result = parse(
+            "\n\n\n== Pressure-Sensitive Floor ==\nAnalyzing...\n\nDoors here lead:\n- south\n\n\
+             A loud, robotic voice says \"Alert! Droids on this ship are lighter than the \
+             detected value!\" and you are ejected back to the checkpoint.\n\n\n\n\
+             == Security Checkpoint ==\nIn the next room, a pressure-sensitive floor will \
+             verify your identity.\n\nDoors here lead:\n- north\n- east\n\nCommand?\n",
+        );
+        assert_eq!(Some(Alert::TooHeavy), result.alert);
+        assert_eq!(2, result.rooms.len());
+        assert_eq!("Security Checkpoint", result.room().unwrap().name);
+
+        let result = parse(
+            "\nA loud, robotic voice says \"Analysis complete! You may proceed.\" and you enter \
+             the cockpit.\nSanta notices your small droid, looks puzzled for a moment, realizes \
+             what has happened, and radios your ship directly.\n\"Oh, hello! You should be able \
+             to get in by typing 134349952 on the keypad at the main airlock.\"\n",
+        );
+        assert_eq!(None, result.alert);
+        assert_eq!(Some("134349952".to_owned()), result.password);
+        assert!(!result.prompt);
+    }
+}
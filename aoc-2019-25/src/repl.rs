@@ -0,0 +1,448 @@
+#![allow(dead_code)]
+
+use crate::computer::{Computer, Intcode};
+use crate::explorer::{send, ExploreError};
+use crate::map::ShipMap;
+use crate::parser;
+
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+const HELP: &str = "\
+Anything not starting with `:` goes to the droid. Tab completes
+directions and items.
+
+  :save <name>  remember this point, also written to the save directory
+  :load <name>  go back to a saved point
+  :undo         take back the last command
+  :map          list the rooms found so far
+  :dot [file]   print the map as a Graphviz graph, or write it to file
+  :inv          list what the droid is carrying
+  :quit         leave
+";
+
+const META_COMMANDS: [&str; 8] = [
+    ":save ", ":load ", ":undo", ":map", ":dot", ":inv", ":help", ":quit",
+];
+
+/// Everything a snapshot needs to put the game back the way it was.
+#[derive(Clone)]
+struct State {
+    computer: Computer,
+    map: ShipMap,
+    here: Option<String>,
+    inventory: Vec<String>,
+    /// Commands that got the game here from the start, which is what
+    /// goes in a save file.
+    log: Vec<String>,
+}
+
+pub struct Session {
+    program: Vec<Intcode>,
+    intro: String,
+    state: State,
+    undo: Vec<State>,
+    saves: HashMap<String, State>,
+    save_dir: PathBuf,
+}
+
+impl Session {
+    pub fn new(program: Vec<Intcode>, save_dir: PathBuf) -> Result<Self, ExploreError> {
+        let (state, intro) = Self::fresh(&program)?;
+        Ok(Session {
+            program,
+            intro,
+            state,
+            undo: vec![],
+            saves: HashMap::new(),
+            save_dir,
+        })
+    }
+
+    fn fresh(program: &[Intcode]) -> Result<(State, String), ExploreError> {
+        let mut computer = Computer::new(program.to_vec());
+        let intro = send(&mut computer, "")?;
+
+        let mut map = ShipMap::new();
+        let here = map.update(None, "", &parser::parse(&intro));
+        let state = State {
+            computer,
+            map,
+            here,
+            inventory: vec![],
+            log: vec![],
+        };
+        Ok((state, intro))
+    }
+
+    fn absorb(&mut self, command: &str, output: &str) {
+        let result = parser::parse(output);
+        let state = &mut self.state;
+
+        state.here = state.map.update(state.here.as_deref(), command, &result);
+        state.inventory.retain(|i| !result.dropped.contains(i));
+        state.inventory.extend(result.taken.iter().cloned());
+        if let Some(inventory) = result.inventory {
+            state.inventory = inventory;
+        }
+        state.log.push(command.to_owned());
+    }
+
+    /// Lines the user could type next, for tab completion.
+    pub fn completions(&self, prefix: &str) -> Vec<String> {
+        let mut candidates: Vec<String> = vec!["inv".to_owned()];
+
+        let room = self
+            .state
+            .here
+            .as_ref()
+            .and_then(|h| self.state.map.room(h));
+        if let Some(room) = room {
+            candidates.extend(room.doors.iter().cloned());
+            candidates.extend(room.items.iter().map(|i| format!("take {}", i)));
+        }
+        candidates.extend(self.state.inventory.iter().map(|i| format!("drop {}", i)));
+        candidates.extend(META_COMMANDS.iter().map(|&c| c.to_owned()));
+        candidates.extend(
+            self.save_names()
+                .into_iter()
+                .map(|n| format!(":load {}", n)),
+        );
+
+        candidates.retain(|c| c.starts_with(prefix));
+        candidates.sort();
+        candidates.dedup();
+        candidates
+    }
+
+    fn game_command(&mut self, command: &str) -> String {
+        if self.state.computer.is_halted() {
+            return "The game is over. Use :undo or :load to carry on.\n".to_owned();
+        }
+
+        let before = self.state.clone();
+        let hazard = command.strip_prefix("take ");
+        match send(&mut self.state.computer, command) {
+            Ok(output) => {
+                self.undo.push(before);
+                self.absorb(command, &output);
+                output
+            }
+            Err(ExploreError::Halted(_, output)) => {
+                self.undo.push(before);
+                self.state.log.push(command.to_owned());
+                if let Some(item) = hazard {
+                    self.state.map.mark_hazard(item);
+                }
+                output + "\nThe game has ended. Use :undo or :load to carry on.\n"
+            }
+            Err(e) => {
+                self.state = before;
+                if let Some(item) = hazard {
+                    self.state.map.mark_hazard(item);
+                }
+                format!("{}. That command has been taken back.\n", e)
+            }
+        }
+    }
+
+    /// Responds to one line of user input.
+    pub fn handle(&mut self, line: &str) -> String {
+        let line = line.trim();
+        let (meta, arg) = match line.split_once(' ') {
+            Some((meta, arg)) => (meta, arg.trim()),
+            None => (line, ""),
+        };
+
+        match (meta, arg) {
+            (":help", _) => HELP.to_owned(),
+            (":inv", _) if self.state.inventory.is_empty() => "Carrying nothing.\n".to_owned(),
+            (":inv", _) => self
+                .state
+                .inventory
+                .iter()
+                .map(|i| format!("- {}\n", i))
+                .collect(),
+            (":map", _) => self.map_listing(),
+            (":dot", "") => self.state.map.to_dot(),
+            (":dot", path) => match fs::write(path, self.state.map.to_dot()) {
+                Ok(()) => format!("Wrote {}.\n", path),
+                Err(e) => format!("Couldn't write {}: {}\n", path, e),
+            },
+            (":undo", _) => match self.undo.pop() {
+                Some(state) => {
+                    self.restore(state);
+                    format!("Back in {}.\n", self.here())
+                }
+                None => "Nothing to undo.\n".to_owned(),
+            },
+            (":save", name) if !name.is_empty() => self.save(name),
+            (":load", name) if !name.is_empty() => self.load(name),
+            (m, _) if m.starts_with(':') => format!("Unknown command {}. Try :help\n", line),
+            _ => self.game_command(line),
+        }
+    }
+
+    pub fn here(&self) -> &str {
+        self.state.here.as_deref().unwrap_or("nowhere")
+    }
+
+    pub fn intro(&self) -> &str {
+        &self.intro
+    }
+
+    fn load(&mut self, name: &str) -> String {
+        let state = match self.saves.get(name) {
+            Some(state) => state.clone(),
+            None => match self.replay(name) {
+                Ok(state) => state,
+                Err(message) => return message,
+            },
+        };
+
+        self.undo.push(self.state.clone());
+        self.restore(state);
+        format!("Loaded {}. In {}.\n", name, self.here())
+    }
+
+    fn map_listing(&self) -> String {
+        let mut rooms: Vec<_> = self.state.map.rooms().collect();
+        rooms.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut sb = String::new();
+        for room in rooms {
+            let marker = if Some(&room.name) == self.state.here.as_ref() {
+                "*"
+            } else {
+                " "
+            };
+            sb += &format!("{} {}\n", marker, room.name);
+            for door in &room.doors {
+                let to = match (
+                    self.state.map.exit(&room.name, door),
+                    self.state.map.floor(),
+                ) {
+                    (Some(to), _) => to,
+                    (None, Some((r, d))) if r == room.name && d == door => "pressure floor",
+                    _ => "?",
+                };
+                sb += &format!("    {} -> {}\n", door, to);
+            }
+            for item in &room.items {
+                let hazard = if self.state.map.is_hazard(item) {
+                    " (dangerous)"
+                } else {
+                    ""
+                };
+                sb += &format!("    item: {}{}\n", item, hazard);
+            }
+        }
+        sb
+    }
+
+    /// Rebuilds a saved game from its command log on disk.
+    fn replay(&self, name: &str) -> Result<State, String> {
+        let path = self.save_path(name);
+        let log = fs::read_to_string(&path).map_err(|_| format!("No save called {}.\n", name))?;
+
+        let (state, _) = Self::fresh(&self.program).map_err(|e| format!("{}\n", e))?;
+        let mut replay = Session {
+            program: vec![],
+            intro: String::new(),
+            state,
+            undo: vec![],
+            saves: HashMap::new(),
+            save_dir: PathBuf::new(),
+        };
+        for command in log.lines().filter(|l| !l.is_empty()) {
+            replay.game_command(command);
+        }
+        Ok(replay.state)
+    }
+
+    /// Swaps in an earlier state, keeping what we've learned about
+    /// which items are dangerous.
+    fn restore(&mut self, state: State) {
+        let hazards: Vec<String> = self.state.map.hazards().map(str::to_owned).collect();
+        self.state = state;
+        for item in hazards {
+            self.state.map.mark_hazard(&item);
+        }
+    }
+
+    fn save(&mut self, name: &str) -> String {
+        self.saves.insert(name.to_owned(), self.state.clone());
+
+        let log: String = self.state.log.iter().map(|c| format!("{}\n", c)).collect();
+        let written =
+            fs::create_dir_all(&self.save_dir).and_then(|_| fs::write(self.save_path(name), log));
+        match written {
+            Ok(()) => format!("Saved {}.\n", name),
+            Err(e) => format!("Saved {} for this session only: {}\n", name, e),
+        }
+    }
+
+    fn save_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.saves.keys().cloned().collect();
+        if let Ok(entries) = fs::read_dir(&self.save_dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().is_some_and(|e| e == "save") {
+                    if let Some(stem) = path.file_stem() {
+                        names.push(stem.to_string_lossy().into_owned());
+                    }
+                }
+            }
+        }
+        names
+    }
+
+    fn save_path(&self, name: &str) -> PathBuf {
+        self.save_dir.join(format!("{}.save", name))
+    }
+}
+
+#[derive(Default)]
+struct ReplHelper {
+    candidates: Vec<String>,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let prefix = &line[..pos];
+        let matches = self
+            .candidates
+            .iter()
+            .filter(|c| c.starts_with(prefix))
+            .cloned()
+            .collect();
+        Ok((0, matches))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+/// Plays the game interactively on the terminal.
+pub fn run(program: Vec<Intcode>, save_dir: PathBuf) -> rustyline::Result<()> {
+    let mut session = Session::new(program, save_dir).unwrap_or_else(|e| panic!("{}", e));
+    print!("{}", session.intro());
+
+    let mut editor = Editor::<ReplHelper, DefaultHistory>::new()?;
+    editor.set_helper(Some(ReplHelper::default()));
+
+    loop {
+        editor.helper_mut().unwrap().candidates = session.completions("");
+
+        match editor.readline("> ") {
+            Ok(line) if line.trim() == ":quit" => return Ok(()),
+            Ok(line) if line.trim().is_empty() => (),
+            Ok(line) => {
+                editor.add_history_entry(line.as_str())?;
+                print!("{}", session.handle(&line));
+            }
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => return Ok(()),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn session(dir: &str) -> Session {
+        let program = Computer::parse_program(&fs::read_to_string("input.txt").unwrap());
+        let save_dir = std::env::temp_dir().join(dir);
+        _ = fs::remove_dir_all(&save_dir);
+        Session::new(program, save_dir).unwrap()
+    }
+
+    #[test]
+    fn undo_save_and_load() {
+        let mut session = session("aoc-2019-25-repl-test");
+        let start = session.here().to_owned();
+        let door = session
+            .completions("")
+            .iter()
+            .find(|c| ["north", "south", "east", "west"].contains(&c.as_str()))
+            .unwrap()
+            .clone();
+
+        assert!(session.handle(&door).contains("=="));
+        let next = session.here().to_owned();
+        assert_ne!(start, next);
+        session.handle(":undo");
+        assert_eq!(start, session.here());
+        assert_eq!("Nothing to undo.\n", session.handle(":undo"));
+
+        session.handle(&door);
+        assert!(session.handle(":save there").starts_with("Saved there."));
+        session.handle(":undo");
+        assert_eq!(start, session.here());
+        session.handle(":load there");
+        assert_eq!(next, session.here());
+
+        // a new session only has the file to go on
+        let save_dir = session.save_dir.clone();
+        let program = session.program.clone();
+        let mut session = Session::new(program, save_dir).unwrap();
+        assert!(session
+            .completions(":lo")
+            .contains(&":load there".to_owned()));
+        session.handle(":load there");
+        assert_eq!(next, session.here());
+        assert!(session.handle(":map").contains(&format!("* {}", next)));
+    }
+
+    #[test]
+    fn hazards_and_completion() {
+        let mut session = session("aoc-2019-25-repl-hazards");
+        assert!(session.handle("bogus").contains("Unrecognized command"));
+        assert!(session.handle(":bogus").starts_with("Unknown command"));
+        assert!(session.handle(":dot").starts_with("digraph ship {"));
+
+        session.handle("south");
+        assert_eq!(vec!["take infinite loop"], session.completions("take "));
+        assert!(session
+            .handle("take infinite loop")
+            .ends_with("That command has been taken back.\n"));
+        assert!(session
+            .handle(":map")
+            .contains("item: infinite loop (dangerous)"));
+
+        session.handle("east");
+        session.handle("take whirled peas");
+        assert_eq!("- whirled peas\n", session.handle(":inv"));
+        assert_eq!(vec!["drop whirled peas"], session.completions("dr"));
+
+        // knowing an item is dangerous survives going back in time
+        session.handle(":undo");
+        session.handle(":undo");
+        assert_eq!("Carrying nothing.\n", session.handle(":inv"));
+        assert!(session.state.map.is_hazard("infinite loop"));
+    }
+}
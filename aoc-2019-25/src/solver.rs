@@ -0,0 +1,185 @@
+#![allow(dead_code)]
+
+use crate::explorer::{ExploreError, Explorer};
+use crate::parser::{self, Alert};
+
+/// Which items to carry, as a bitmask over the item list.
+pub type Selection = u64;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Solution {
+    pub selection: Selection,
+    pub attempts: usize,
+}
+
+/// Finds the selection of `count` items the floor accepts.
+///
+/// Candidates are visited in Gray-code order, so consecutive tries
+/// usually differ by a single item. Any candidate that is a superset of
+/// a set already found too heavy, or a subset of one found too light,
+/// is skipped without being tried. `weigh` reports the floor's verdict
+/// on a selection, `None` meaning it let the droid through.
+pub fn solve(count: usize, mut weigh: impl FnMut(Selection) -> Option<Alert>) -> Option<Solution> {
+    assert!(count < Selection::BITS as usize, "too many items");
+
+    let mut too_heavy: Vec<Selection> = vec![];
+    let mut too_light: Vec<Selection> = vec![];
+    let mut attempts = 0;
+
+    for i in 0..(1 as Selection) << count {
+        let selection = i ^ (i >> 1);
+        if too_heavy.iter().any(|&h| h & !selection == 0)
+            || too_light.iter().any(|&l| selection & !l == 0)
+        {
+            continue;
+        }
+
+        attempts += 1;
+        match weigh(selection) {
+            None => {
+                return Some(Solution {
+                    selection,
+                    attempts,
+                })
+            }
+            Some(Alert::TooHeavy) => too_heavy.push(selection),
+            Some(Alert::TooLight) => too_light.push(selection),
+        }
+    }
+
+    None
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub password: String,
+    pub carried: Vec<String>,
+    pub attempts: usize,
+}
+
+/// Gets past the pressure floor by picking up and putting down the
+/// explorer's inventory. The explorer must be standing at the
+/// checkpoint, next to the floor.
+pub fn solve_checkpoint(explorer: &mut Explorer) -> Result<Checkpoint, ExploreError> {
+    let (checkpoint, floor) = explorer.floor().expect("expected to have found the floor");
+    assert_eq!(checkpoint, explorer.here());
+    let floor = floor.to_owned();
+
+    let items = explorer.inventory().to_vec();
+    let mut held: Selection = (1 << items.len()) - 1;
+    let mut password = None;
+    let mut failure = None;
+
+    let solution = solve(items.len(), |selection| {
+        if password.is_some() || failure.is_some() {
+            return None;
+        }
+
+        let mut commands = vec![];
+        for (i, item) in items.iter().enumerate() {
+            match (held & 1 << i != 0, selection & 1 << i != 0) {
+                (false, true) => commands.push(format!("take {}", item)),
+                (true, false) => commands.push(format!("drop {}", item)),
+                _ => (),
+            }
+        }
+        commands.push(floor.clone());
+        held = selection;
+
+        let output = match explorer.send(&commands.join("\n")) {
+            // Getting through ends the game.
+            Ok(output) | Err(ExploreError::Halted(_, output)) => output,
+            Err(e) => {
+                failure = Some(e);
+                return None;
+            }
+        };
+
+        let result = parser::parse(&output);
+        if result.alert.is_none() {
+            password = result.password;
+        }
+        result.alert
+    });
+
+    if let Some(e) = failure {
+        return Err(e);
+    }
+    let (Some(solution), Some(password)) = (solution, password) else {
+        return Err(ExploreError::Unexpected(
+            floor,
+            "no combination of items got past the floor".to_owned(),
+        ));
+    };
+
+    Ok(Checkpoint {
+        password,
+        carried: items
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| solution.selection & 1 << i != 0)
+            .map(|(_, item)| item)
+            .collect(),
+        attempts: solution.attempts,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::computer::Computer;
+    use crate::explorer::CHECKPOINT;
+    use std::fs;
+
+    fn scale(weights: &[u32], target: u32) -> impl Fn(Selection) -> Option<Alert> + '_ {
+        move |selection| {
+            let total: u32 = (0..weights.len())
+                .filter(|i| selection & 1 << i != 0)
+                .map(|i| weights[i])
+                .sum();
+            match total.cmp(&target) {
+                std::cmp::Ordering::Less => Some(Alert::TooLight),
+                std::cmp::Ordering::Equal => None,
+                std::cmp::Ordering::Greater => Some(Alert::TooHeavy),
+            }
+        }
+    }
+
+    #[test]
+    fn solve_prunes() {
+        let weights = [1, 2, 4, 8, 16, 32, 64, 128, 256, 512, 1024, 2048];
+        let solution = solve(weights.len(), scale(&weights, 0b1010_0110_0101)).unwrap();
+        assert_eq!(0b1010_0110_0101, solution.selection);
+        assert!(solution.attempts < 1 << weights.len());
+
+        let mut tried = 0;
+        assert_eq!(
+            None,
+            solve(3, |_| {
+                tried += 1;
+                Some(Alert::TooHeavy)
+            })
+        );
+        // everything is a superset of the empty set
+        assert_eq!(1, tried);
+    }
+
+    #[test]
+    fn solve_adventure() {
+        let program = Computer::parse_program(&fs::read_to_string("input.txt").unwrap());
+        let mut explorer = Explorer::new(program).unwrap();
+        explorer.explore().unwrap();
+        explorer.walk_to(CHECKPOINT).unwrap();
+
+        let checkpoint = solve_checkpoint(&mut explorer).unwrap();
+        assert_eq!("134349952", checkpoint.password);
+        let mut carried = checkpoint.carried;
+        carried.sort();
+        assert_eq!(
+            vec!["astrolabe", "hologram", "klein bottle", "tambourine"],
+            carried
+        );
+        assert!(checkpoint.attempts < 256);
+    }
+}